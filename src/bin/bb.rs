@@ -21,7 +21,8 @@ fn main() {
             color::map(1, [0.0, 0.0, 1.0]),
             color::map(2, [0.0, 1.0, 0.0])
         ],
-        neighborhood: Neighborhood::Moore
+        neighborhood: Neighborhood::Moore { radius: 1 },
+        ..Default::default()
     };
     
     pollster::block_on(run(automata, config));
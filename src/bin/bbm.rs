@@ -0,0 +1,26 @@
+use gridded_automata::{
+    run,
+    automata, 
+    Config, 
+    color, 
+    Neighborhood
+};
+
+fn main() {
+    let automata = automata::random_automata_with_padding(
+        automata::Size { width: 512, height: 512 },
+        &[0, 0, 0, 0, 0, 0, 0, 1],
+        192
+    );
+
+    let config = Config {
+        title: Some("Billiard Ball Machine".into()),
+        fps: 60,
+        state_shader: include_str!("bbm.wgsl").into(),
+        coloring: &[color::alive([1.0; 3])],
+        neighborhood: Neighborhood::Margolus,
+        ..Default::default()
+    };
+    
+    pollster::block_on(run(automata, config));
+}
@@ -0,0 +1,15 @@
+// A lone ball moves through the block to the opposite corner
+// Two balls meeting head-on along a diagonal bounce off along the other diagonal
+fn block_rule(cells: array<u32, 4>) -> array<u32, 4> {
+    let count = cells[0] + cells[1] + cells[2] + cells[3];
+
+    if(count == 1u) {
+        return array<u32, 4>(cells[3], cells[2], cells[1], cells[0]);
+    }
+
+    if(count == 2u && cells[0] == cells[3]) {
+        return array<u32, 4>(cells[1], cells[0], cells[3], cells[2]);
+    }
+
+    return cells;
+}
@@ -0,0 +1,27 @@
+use gridded_automata::{
+    run,
+    automata, 
+    rules,
+    Config
+};
+
+fn main() {
+    let automata = automata::random_automata_with_padding(
+        automata::Size { width: 512, height: 512 },
+        &[0, 1],
+        192
+    );
+
+    let rule = "R5,C0,M1,S34..58,B34..45,NM".parse::<rules::Rule>().unwrap();
+
+    let config = Config {
+        title: Some("Bosco's Rule".into()),
+        fps: 30,
+        state_shader: rule.state_shader(),
+        coloring: &rule.coloring(),
+        neighborhood: rule.neighborhood(),
+        ..Default::default()
+    };
+    
+    pollster::block_on(run(automata, config));
+}
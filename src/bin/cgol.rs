@@ -1,12 +1,20 @@
+use std::{ env, io };
+
 use gridded_automata::{
     run,
-    automata, 
-    Config, 
-    color, 
+    automata,
+    backend::Backend,
+    headless::Headless,
+    stream,
+    Config,
+    color,
     Neighborhood
 };
 
-fn main() {
+// Frames streamed by `--y4m` when no count follows it
+const GENERATIONS: u32 = 1000;
+
+fn main() -> anyhow::Result<()> {
     let automata = automata::random_automata(
         automata::Size { width: 512, height: 512 },
         &[0, 1]
@@ -17,8 +25,36 @@ fn main() {
         fps: 60,
         state_shader: include_str!("cgol.wgsl").into(),
         coloring: &[color::lerp(1..=6, [1.0, 0.2, 0.0], [0.1, 0.2, 1.0])],
-        neighborhood: Neighborhood::Moore
+        neighborhood: Neighborhood::Moore { radius: 1 },
+        ..Default::default()
     };
-    
+
+    // `--y4m [generations]` writes the run to stdout as Y4M instead of opening a window
+    let mut args = env::args().skip(1);
+    if args.next().as_deref() == Some("--y4m") {
+        let generations = match args.next() {
+            Some(count) => count.parse::<u32>()?,
+            None => GENERATIONS
+        };
+
+        let size = automata.size();
+        let mut simulation = pollster::block_on(Headless::new(automata, &config, false))?;
+        let mut stream = stream::Stream::new(io::stdout().lock(), stream::Format::Y4m, size, config.fps);
+
+        // Reading every frame back stalls the GPU once per generation, which is fine for an example
+        for _ in 0..generations {
+            simulation.step();
+            let frame = simulation
+                .read_frame()
+                .ok_or_else(|| anyhow::anyhow!("the frame couldn't be read back"))?;
+
+            stream.write_frame(&frame)?;
+        }
+
+        return anyhow::Ok(());
+    }
+
     pollster::block_on(run(automata, config));
-}
\ No newline at end of file
+
+    anyhow::Ok(())
+}
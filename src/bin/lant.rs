@@ -1,3 +1,5 @@
+use cgmath::Point2;
+
 use gridded_automata::{
     run,
     automata, 
@@ -10,7 +12,7 @@ fn main() {
     let size = automata::Size { width: 128, height: 128 };
 
     let mut automata = automata::Automata::new(size);
-    automata[(size.width/ 2, size.height / 2).into()] = 2;
+    automata[Point2::new(size.width/ 2, size.height / 2)] = 2;
 
     let config = Config {
         title: Some("Langton's Ant".into()),
@@ -22,7 +24,8 @@ fn main() {
             color::map(5, [1.0; 3]),
             color::map_range(6..=9, [1.0, 0.0, 0.0])
         ],
-        neighborhood: Neighborhood::Moore
+        neighborhood: Neighborhood::Moore { radius: 1 },
+        ..Default::default()
     };
     
     pollster::block_on(run(automata, config));
@@ -1,8 +1,8 @@
 use gridded_automata::{
     run,
     automata, 
+    rules,
     Config, 
-    color, 
     Neighborhood
 };
 
@@ -13,13 +13,16 @@ fn main() {
         192
     );
 
+    let rule = "B2/S".parse::<rules::Rule>().unwrap();
+
     let config = Config {
         title: Some("Seeds".into()),
         fps: 60,
-        state_shader: include_str!("seeds.wgsl").into(),
-        coloring: &[color::alive([1.0; 3])],
-        neighborhood: Neighborhood::Moore
+        state_shader: rule.state_shader(),
+        coloring: &rule.coloring(),
+        neighborhood: Neighborhood::Moore { radius: 1 },
+        ..Default::default()
     };
     
     pollster::block_on(run(automata, config));
-}
\ No newline at end of file
+}
@@ -2,6 +2,7 @@ use gridded_automata::{
     run,
     color,
     automata::automata_from_pgm, 
+    Boundary,
     Config, 
     Neighborhood
 };
@@ -18,7 +19,10 @@ fn main() {
             color::map(2, [1.0; 3]),
             color::map(3, [0.0, 0.2, 1.0])
         ],
-        neighborhood: Neighborhood::Moore
+        neighborhood: Neighborhood::Moore { radius: 1 },
+        // Signals shouldn't wrap around the edges of the circuit
+        boundary: Boundary::Constant(0),
+        ..Default::default()
     };
     
     pollster::block_on(run(automata, config));
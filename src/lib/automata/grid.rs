@@ -0,0 +1,447 @@
+use std::{
+    borrow,
+    fmt::Write as _,
+    fs,
+    io::{ self, BufRead, Read, Write }
+};
+
+use crate::{
+    rules,
+    Boundary
+};
+
+use super::{ Automata, Size };
+
+const MAGIC: &[u8; 6] = b"GAGRID";
+const VERSION: u8 = 1;
+
+// How the packed cells are compressed
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Compression {
+    // PackBits, one row at a time, which suits grids with long stretches of one state
+    RunLength,
+    // Deflate over the whole grid, slower but smaller for busy grids
+    Deflate
+}
+
+// Everything stored ahead of the cells
+#[derive(Clone)]
+pub struct Header {
+    pub size: Size,
+    // Bits per cell, one of 1, 2, 4, 8, 16 or 32
+    pub bits: u8,
+    pub compression: Compression,
+    pub boundary: Boundary,
+    // Stored as its rulestring
+    pub rule: Option<rules::Rule>
+}
+
+impl Header {
+    // Picks the fewest bits that hold every state in `automata`
+    pub fn new(
+        automata: &Automata,
+        compression: Compression,
+        boundary: Boundary,
+        rule: Option<rules::Rule>
+    ) -> Self {
+        let max = automata.data.iter().copied().max().unwrap_or(0);
+        let bits = [1, 2, 4, 8, 16, 32]
+            .into_iter()
+            .find(|&bits: &u8| bits == 32 || max >> bits == 0)
+            .unwrap();
+
+        Self { size: automata.size, bits, compression, boundary, rule }
+    }
+
+    fn encode(&self) -> anyhow::Result<Vec<u8>> {
+        let (boundary, state) = match self.boundary {
+            Boundary::Torus => (0, 0),
+            Boundary::Constant(state) => (1, state),
+            Boundary::Reflect => (2, 0),
+            Boundary::Cylinder => (3, 0),
+            Boundary::KleinBottle => (4, 0)
+        };
+
+        let mut rule = String::new();
+        if let Some(r) = &self.rule {
+            write!(rule, "{}", r).map_err(|_| anyhow::anyhow!("the rule can't be written as a rulestring"))?;
+        }
+
+        let length = u16::try_from(rule.len())
+            .map_err(|_| anyhow::anyhow!("the rulestring is longer than {} bytes", u16::MAX))?;
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.extend(self.size.width.to_le_bytes());
+        bytes.extend(self.size.height.to_le_bytes());
+        bytes.push(self.bits);
+        bytes.push(match self.compression {
+            Compression::RunLength => 0,
+            Compression::Deflate => 1
+        } );
+        bytes.push(boundary);
+        bytes.extend(state.to_le_bytes());
+        bytes.extend(length.to_le_bytes());
+        bytes.extend(rule.bytes());
+
+        anyhow::Ok(bytes)
+    }
+
+    // Reads the header off the front of `source`, feeding every byte to the checksum
+    fn decode<R: Read>(source: &mut R, crc: &mut crc32fast::Hasher) -> anyhow::Result<Self> {
+        let mut read = |count: usize| -> anyhow::Result<Vec<u8>> {
+            let mut bytes = vec![0; count];
+            source
+                .read_exact(&mut bytes)
+                .map_err(|_| anyhow::anyhow!("the grid ends in the middle of its header"))?;
+
+            crc.update(&bytes);
+            anyhow::Ok(bytes)
+        };
+
+        let u32 = |bytes: &[u8]| u32::from_le_bytes(bytes.try_into().unwrap());
+
+        if read(MAGIC.len())? != MAGIC {
+            anyhow::bail!("the file is not a grid");
+        }
+
+        let version = read(1)?[0];
+        if version > VERSION {
+            anyhow::bail!("grid version {} is newer than the {} this library reads", version, VERSION);
+        }
+
+        let fields = read(17)?;
+        let size = Size { width: u32(&fields[0..4]), height: u32(&fields[4..8]) };
+
+        let bits = fields[8];
+        if ![1, 2, 4, 8, 16, 32].contains(&bits) {
+            anyhow::bail!("{} is not a supported number of bits per cell", bits);
+        }
+
+        let compression = match fields[9] {
+            0 => Compression::RunLength,
+            1 => Compression::Deflate,
+            compression => anyhow::bail!("{} is not a known compression", compression)
+        };
+
+        let boundary = match (fields[10], u32(&fields[11..15])) {
+            (0, _) => Boundary::Torus,
+            (1, state) => Boundary::Constant(state),
+            (2, _) => Boundary::Reflect,
+            (3, _) => Boundary::Cylinder,
+            (4, _) => Boundary::KleinBottle,
+            (boundary, _) => anyhow::bail!("{} is not a known boundary", boundary)
+        };
+
+        let length = u16::from_le_bytes([fields[15], fields[16]]) as usize;
+        let rule = match String::from_utf8(read(length)?)? {
+            rule if rule.is_empty() => None,
+            rule => Some(rule.parse::<rules::Rule>()?)
+        };
+
+        anyhow::Ok(Self { size, bits, compression, boundary, rule })
+    }
+
+    // Bytes per packed row, rows are padded to a whole byte
+    fn row_bytes(&self) -> usize {
+        (self.size.width as usize * self.bits as usize).div_ceil(8)
+    }
+}
+
+// Packs a row of states, most significant bits first below a byte and little-endian above one
+fn pack(row: &[u32], bits: u8, packed: &mut Vec<u8>) {
+    packed.clear();
+    match bits {
+        1 | 2 | 4 => for chunk in row.chunks(8 / bits as usize) {
+            let byte = chunk
+                .iter()
+                .enumerate()
+                .fold(0u8, |byte, (i, &state)| byte | ((state as u8) << (8 - bits as usize * (i + 1))));
+
+            packed.push(byte);
+        },
+        _ => for &state in row {
+            packed.extend_from_slice(&state.to_le_bytes()[..bits as usize / 8]);
+        }
+    }
+}
+
+fn unpack(packed: &[u8], bits: u8, row: &mut [u32]) {
+    match bits {
+        1 | 2 | 4 => {
+            let mask = (1u8 << bits) - 1;
+            let per_byte = 8 / bits as usize;
+            for (i, state) in row.iter_mut().enumerate() {
+                let shift = 8 - bits as usize * (i % per_byte + 1);
+                *state = ((packed[i / per_byte] >> shift) & mask) as u32;
+            }
+        },
+        _ => for (state, bytes) in row.iter_mut().zip(packed.chunks_exact(bits as usize / 8)) {
+            let mut le = [0; 4];
+            le[..bytes.len()].copy_from_slice(bytes);
+            *state = u32::from_le_bytes(le);
+        }
+    }
+}
+
+// PackBits: a control byte of n below 128 is followed by n + 1 literal bytes,
+// and one above 128 by a single byte repeated 257 - n times
+fn pack_bits<W: Write>(row: &[u8], sink: &mut W) -> io::Result<()> {
+    let run = |i: usize| row[i..].iter().take(128).take_while(|&&byte| byte == row[i]).count();
+
+    let mut i = 0;
+    while i < row.len() {
+        let length = run(i);
+        if length >= 3 {
+            sink.write_all(&[(257 - length) as u8, row[i]])?;
+            i += length;
+            continue;
+        }
+
+        // Literals run up to the next stretch worth repeating
+        let start = i;
+        while i < row.len() && i - start < 128 && (i == start || run(i) < 3) {
+            i += 1;
+        }
+
+        sink.write_all(&[(i - start - 1) as u8])?;
+        sink.write_all(&row[start..i])?;
+    }
+
+    io::Result::Ok(())
+}
+
+fn unpack_bits<R: Read>(source: &mut R, row: &mut [u8]) -> anyhow::Result<()> {
+    let mut byte = || -> anyhow::Result<u8> {
+        let mut byte = [0];
+        source
+            .read_exact(&mut byte)
+            .map_err(|_| anyhow::anyhow!("the grid ends in the middle of its cells"))?;
+
+        anyhow::Ok(byte[0])
+    };
+
+    let mut i = 0;
+    while i < row.len() {
+        let control = byte()? as usize;
+        let length = match control {
+            0..=127 => control + 1,
+            128 => continue,
+            _ => 257 - control
+        };
+
+        if i + length > row.len() {
+            anyhow::bail!("a run goes past the end of its row");
+        }
+
+        if control < 128 {
+            for cell in &mut row[i..i + length] {
+                *cell = byte()?;
+            }
+        } else {
+            row[i..i + length].fill(byte()?);
+        }
+
+        i += length;
+    }
+
+    anyhow::Ok(())
+}
+
+// Writes `automata` to `sink` one row at a time, ending with a CRC-32 of the header and packed cells
+pub fn write<W: Write>(mut sink: W, automata: &Automata, header: &Header) -> anyhow::Result<()> {
+    if (header.size.width, header.size.height) != (automata.size.width, automata.size.height) {
+        anyhow::bail!(
+            "the header is for a {}x{} grid, not {}x{}",
+            header.size.width, header.size.height, automata.size.width, automata.size.height
+        );
+    }
+
+    if let Some(state) = automata.data.iter().find(|&&state| header.bits < 32 && state >> header.bits != 0) {
+        anyhow::bail!("state {} doesn't fit in {} bits", state, header.bits);
+    }
+
+    let mut crc = crc32fast::Hasher::new();
+    let bytes = header.encode()?;
+    crc.update(&bytes);
+    sink.write_all(&bytes)?;
+
+    let mut packed = Vec::with_capacity(header.row_bytes());
+    let rows = automata.data.chunks_exact(automata.size.width.max(1) as usize);
+    match header.compression {
+        Compression::RunLength => for row in rows {
+            pack(row, header.bits, &mut packed);
+            crc.update(&packed);
+            pack_bits(&packed, &mut sink)?;
+        },
+        Compression::Deflate => {
+            let mut encoder = flate2::write::DeflateEncoder::new(&mut sink, flate2::Compression::default());
+            for row in rows {
+                pack(row, header.bits, &mut packed);
+                crc.update(&packed);
+                encoder.write_all(&packed)?;
+            }
+
+            encoder.finish()?;
+        }
+    }
+
+    sink.write_all(&crc.finalize().to_le_bytes())?;
+
+    anyhow::Ok(sink.flush()?)
+}
+
+// Reads a grid from `source` one row at a time, checking it against its CRC-32
+// Grids past `max_cells` are refused before anything is allocated, so a corrupt header can't ask for
+// more than the caller can hold, such as `storage_buffer_cells` of the device the grid will run on
+pub fn read<R: BufRead>(mut source: R, max_cells: u64) -> anyhow::Result<(Automata, Header)> {
+    let mut crc = crc32fast::Hasher::new();
+    let header = Header::decode(&mut source, &mut crc)?;
+
+    if header.size.width as u64 * header.size.height as u64 > max_cells {
+        anyhow::bail!(
+            "a {}x{} grid is past the limit of {} cells",
+            header.size.width, header.size.height, max_cells
+        );
+    }
+
+    let mut automata = Automata::new(header.size);
+    let mut packed = vec![0; header.row_bytes()];
+    let rows = automata.data.chunks_exact_mut(header.size.width.max(1) as usize);
+    match header.compression {
+        Compression::RunLength => for row in rows {
+            unpack_bits(&mut source, &mut packed)?;
+            crc.update(&packed);
+            unpack(&packed, header.bits, row);
+        },
+        Compression::Deflate => {
+            let mut decoder = flate2::bufread::DeflateDecoder::new(&mut source);
+            for row in rows {
+                decoder
+                    .read_exact(&mut packed)
+                    .map_err(|_| anyhow::anyhow!("the grid ends in the middle of its cells"))?;
+
+                crc.update(&packed);
+                unpack(&packed, header.bits, row);
+            }
+
+            // Runs the decoder to the end of its stream, so the checksum comes next
+            if decoder.read(&mut [0])? != 0 {
+                anyhow::bail!("the grid has more cells than its header describes");
+            }
+        }
+    }
+
+    let mut checksum = [0; 4];
+    source
+        .read_exact(&mut checksum)
+        .map_err(|_| anyhow::anyhow!("the grid is missing its checksum"))?;
+
+    if u32::from_le_bytes(checksum) != crc.finalize() {
+        anyhow::bail!("the grid doesn't match its checksum");
+    }
+
+    anyhow::Ok((automata, header))
+}
+
+pub fn automata_from_grid<C: Into<borrow::Cow<'static, str>>>(
+    file: C,
+    max_cells: u64
+) -> anyhow::Result<(Automata, Header)> {
+    read(io::BufReader::new(fs::File::open(&*file.into())?), max_cells)
+}
+
+impl Automata {
+    pub fn save_grid<C: Into<borrow::Cow<'static, str>>>(
+        &self,
+        file: C,
+        compression: Compression,
+        boundary: Boundary,
+        rule: Option<rules::Rule>
+    ) -> anyhow::Result<()> {
+        let header = Header::new(self, compression, boundary, rule);
+        write(io::BufWriter::new(fs::File::create(&*file.into())?), self, &header)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(automata: &Automata, compression: Compression) -> (Automata, Header) {
+        let header = Header::new(automata, compression, Boundary::Constant(3), "B36/S23".parse().ok());
+        let mut bytes = Vec::new();
+        write(&mut bytes, automata, &header).unwrap();
+
+        read(&bytes[..], u64::MAX).unwrap()
+    }
+
+    fn bytes(automata: &Automata, compression: Compression) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write(&mut bytes, automata, &Header::new(automata, compression, Boundary::Torus, None)).unwrap();
+
+        bytes
+    }
+
+    #[test]
+    fn round_trip_every_depth_and_compression() {
+        // An odd width leaves part of the last byte of each row as padding
+        for (max, bits) in [(1, 1), (3, 2), (15, 4), (255, 8), (65535, 16), (u32::MAX, 32)] {
+            let mut automata = Automata::new((13, 5).into());
+            for (i, state) in automata.data.iter_mut().enumerate() {
+                // Long runs for PackBits next to noise for the literals
+                *state = if i % 13 < 6 { max } else { (i as u32).wrapping_mul(2654435761) % max.saturating_add(1) };
+            }
+
+            for compression in [Compression::RunLength, Compression::Deflate] {
+                let (loaded, header) = round_trip(&automata, compression);
+
+                assert_eq!(loaded.data, automata.data, "{} states, {:?}", max, compression);
+                assert_eq!(header.bits, bits);
+                assert_eq!(header.compression, compression);
+                assert_eq!(header.rule.map(|rule| rule.to_string()), Some("B36/S23".to_string()));
+                assert!(matches!(header.boundary, Boundary::Constant(3)));
+            }
+        }
+    }
+
+    #[test]
+    fn detects_corruption() {
+        let mut automata = Automata::new((16, 16).into());
+        automata.data[17] = 9;
+
+        for compression in [Compression::RunLength, Compression::Deflate] {
+            let mut bytes = bytes(&automata, compression);
+            let checksum = bytes.len() - 4;
+            bytes[checksum] ^= 1;
+
+            let error = read(&bytes[..], u64::MAX).err().unwrap().to_string();
+            assert_eq!(error, "the grid doesn't match its checksum");
+        }
+    }
+
+    #[test]
+    fn detects_truncation() {
+        let mut automata = Automata::new((16, 16).into());
+        automata.data[17] = 9;
+
+        for compression in [Compression::RunLength, Compression::Deflate] {
+            let bytes = bytes(&automata, compression);
+
+            for end in [10, 30, bytes.len() - 6, bytes.len() - 2] {
+                assert!(read(&bytes[..end], u64::MAX).is_err(), "{:?} cut at {}", compression, end);
+            }
+        }
+    }
+
+    #[test]
+    fn refuses_grids_past_the_limit() {
+        let bytes = bytes(&Automata::new((16, 16).into()), Compression::RunLength);
+
+        assert!(read(&bytes[..], 256).is_ok());
+        assert_eq!(
+            read(&bytes[..], 255).err().unwrap().to_string(),
+            "a 16x16 grid is past the limit of 255 cells"
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use std::{
+    borrow,
+    fmt::Write,
+    fs
+};
+
+use crate::rules;
+
+use super::{
+    pattern,
+    Pattern,
+    plaintext
+};
+
+fn parse_life_105(life: &str) -> anyhow::Result<Pattern> {
+    let mut cells = Vec::new();
+    let mut rule = None;
+
+    // Each `#P` line starts a new block of rows at the given position
+    let (mut x, mut y) = (0, 0);
+    for line in life.lines().map(str::trim) {
+        if let Some(position) = line.strip_prefix("#P") {
+            let position = position
+                .split_whitespace()
+                .map(|value| value.parse::<i32>())
+                .collect::<Result<Vec<_>, _>>()?;
+
+            match position[..] {
+                [px, py] => (x, y) = (px, py),
+                _ => anyhow::bail!("'{}' is not a valid block position", line)
+            }
+        } else if line.starts_with("#N") {
+            rule = Some("B3/S23".parse::<rules::Rule>()?);
+        } else if let Some(rulestring) = line.strip_prefix("#R") {
+            rule = pattern::rule(rulestring)?;
+        } else if !line.starts_with('#') {
+            cells.extend(plaintext::row(line, &['*'])?.into_iter().map(|dx| ((x + dx, y), 1)));
+            y += 1;
+        }
+    }
+
+    anyhow::Ok(Pattern::sparse(cells, rule))
+}
+
+fn parse_life_106(life: &str) -> anyhow::Result<Pattern> {
+    let mut cells = Vec::new();
+    for line in life.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let cell = line
+            .split_whitespace()
+            .map(|value| value.parse::<i32>())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match cell[..] {
+            [x, y] => cells.push(((x, y), 1)),
+            _ => anyhow::bail!("'{}' is not a valid cell", line)
+        }
+    }
+
+    anyhow::Ok(Pattern::sparse(cells, None))
+}
+
+// Reads a pattern from a Life 1.05 file
+// Patterns reaching into negative coordinates are shifted so that they start at zero
+pub fn pattern_from_life_105<C: Into<borrow::Cow<'static, str>>>(file: C) -> anyhow::Result<Pattern> {
+    parse_life_105(&fs::read_to_string(&*file.into())?)
+}
+
+// Reads a pattern from a Life 1.06 file, see `pattern_from_life_105`
+pub fn pattern_from_life_106<C: Into<borrow::Cow<'static, str>>>(file: C) -> anyhow::Result<Pattern> {
+    parse_life_106(&fs::read_to_string(&*file.into())?)
+}
+
+impl Pattern {
+    // Writes the pattern out as a single block in the Life 1.05 format,
+    // positioned at its top-left living cell
+    pub fn to_life_105(&self) -> anyhow::Result<String> {
+        self.two_state()?;
+
+        let mut life = "#Life 1.05\n".to_string();
+        if let Some(rule) = &self.rule {
+            writeln!(life, "#R {}", rule)
+                .map_err(|_| anyhow::anyhow!("the rule can't be written as a rulestring"))?;
+        }
+
+        let min_x = self.cells.iter().map(|(cell, _)| cell.x).min().unwrap_or(0);
+        let min_y = self.cells.iter().map(|(cell, _)| cell.y).min().unwrap_or(0);
+        writeln!(life, "#P {} {}", min_x, min_y)?;
+
+        let rows = plaintext::rows(self, '*');
+        let last = self.cells.iter().map(|(cell, _)| cell.y).max().unwrap_or(0);
+        for row in rows.iter().take(last as usize + 1).skip(min_y as usize) {
+            // Rows that are empty still need a cell to keep their place
+            match row[min_x as usize..].trim_end_matches('.') {
+                "" => life.push('.'),
+                row => life.push_str(row)
+            }
+
+            life.push('\n');
+        }
+
+        anyhow::Ok(life)
+    }
+
+    // Writes the pattern out as a list of living cells in the Life 1.06 format
+    pub fn to_life_106(&self) -> anyhow::Result<String> {
+        self.two_state()?;
+
+        let mut life = "#Life 1.06\n".to_string();
+        for (cell, _) in &self.cells {
+            writeln!(life, "{} {}", cell.x, cell.y)?;
+        }
+
+        anyhow::Ok(life)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(pattern: &Pattern) -> Vec<(u32, u32)> {
+        let mut cells = pattern.cells.iter().map(|(cell, _)| (cell.x, cell.y)).collect::<Vec<_>>();
+        cells.sort_unstable();
+
+        cells
+    }
+
+    #[test]
+    fn life_105_round_trip() {
+        // Dead space to the top-left, and a blank row and column inside the block
+        let mut pattern = Pattern::sparse(vec![((3, 2), 1), ((5, 2), 1), ((3, 4), 1), ((6, 5), 1)], None);
+        pattern.rule = "B36/S23".parse().ok();
+
+        let life = pattern.to_life_105().unwrap();
+        assert_eq!(life, "#Life 1.05\n#R B36/S23\n#P 3 2\n*.*\n.\n*\n...*\n");
+
+        let loaded = parse_life_105(&life).unwrap();
+        assert_eq!(cells(&loaded), cells(&pattern));
+        assert_eq!((loaded.size.width, loaded.size.height), (7, 6));
+        assert_eq!(loaded.rule.map(|rule| rule.to_string()), Some("B36/S23".to_string()));
+    }
+
+    #[test]
+    fn life_105_blocks_can_start_left_of_zero() {
+        let pattern = parse_life_105("#Life 1.05\n#N\n#P -2 -1\n.*\n\n#P 1 3\n*\n").unwrap();
+
+        assert_eq!(cells(&pattern), [(0, 0), (2, 4)]);
+        assert_eq!(pattern.rule.map(|rule| rule.to_string()), Some("B3/S23".to_string()));
+    }
+
+    #[test]
+    fn life_105_rules() {
+        assert!(parse_life_105("#Life 1.05\n#R WireWorld\n*\n").unwrap().rule.is_none());
+        assert!(parse_life_105("#Life 1.05\n#R B3/S2x\n*\n").is_err());
+    }
+
+    #[test]
+    fn life_106_round_trip() {
+        // Negative coordinates are shifted over to start at zero
+        let pattern = parse_life_106("#Life 1.06\n-2 -1\n0 0\n\n1 -1\n").unwrap();
+        assert_eq!(cells(&pattern), [(0, 0), (2, 1), (3, 0)]);
+
+        let loaded = parse_life_106(&pattern.to_life_106().unwrap()).unwrap();
+        assert_eq!(cells(&loaded), cells(&pattern));
+        assert_eq!((loaded.size.width, loaded.size.height), (4, 2));
+    }
+
+    #[test]
+    fn life_106_keeps_leading_dead_space() {
+        let pattern = Pattern::sparse(vec![((4, 3), 1)], None);
+        let loaded = parse_life_106(&pattern.to_life_106().unwrap()).unwrap();
+
+        assert_eq!(cells(&loaded), [(4, 3)]);
+        assert_eq!((loaded.size.width, loaded.size.height), (5, 4));
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        assert!(parse_life_105("#Life 1.05\n#P 1\n*\n").is_err());
+        assert!(parse_life_105("#Life 1.05\n#P 1 x\n*\n").is_err());
+        assert!(parse_life_105("#Life 1.05\n*O*\n").is_err());
+        assert!(parse_life_106("#Life 1.06\n1 2 3\n").is_err());
+        assert!(parse_life_106("#Life 1.06\n1\n").is_err());
+        assert!(parse_life_106("#Life 1.06\n1 y\n").is_err());
+    }
+
+    #[test]
+    fn refuses_multi_state_patterns() {
+        let pattern = Pattern::sparse(vec![((0, 0), 1), ((1, 0), 2)], None);
+
+        assert!(pattern.to_life_105().is_err());
+        assert!(pattern.to_life_106().is_err());
+    }
+}
@@ -0,0 +1,325 @@
+use std::{
+    borrow,
+    fs
+};
+
+use cgmath::Point2;
+
+use crate::rules;
+
+use super::{ pattern, Automata };
+
+enum Node {
+    // An 8x8 block of two-state cells, as the coordinates of its living cells
+    Leaf(Vec<(u64, u64)>),
+    // A 2x2 block of multi-state cells, in the order nw, ne, sw, se
+    States([u32; 4]),
+    // Four nodes one level down, in the same order, where 0 is an empty node
+    Branch(u32, [usize; 4])
+}
+
+impl Node {
+    fn level(&self) -> u32 {
+        match self {
+            Node::Leaf(_) => 3,
+            Node::States(_) => 1,
+            &Node::Branch(level, _) => level
+        }
+    }
+}
+
+// The smallest and largest coordinates of a node's living cells, relative to its corner
+type Bounds = Option<((u64, u64), (u64, u64))>;
+
+fn union(a: Bounds, b: Bounds) -> Bounds {
+    match (a, b) {
+        (Some((a_min, a_max)), Some((b_min, b_max))) => Some((
+            (a_min.0.min(b_min.0), a_min.1.min(b_min.1)),
+            (a_max.0.max(b_max.0), a_max.1.max(b_max.1))
+        )),
+        _ => a.or(b)
+    }
+}
+
+fn point(x: u64, y: u64) -> Bounds {
+    Some(((x, y), (x, y)))
+}
+
+// The offset of each child from its parent's corner, in units of the child's size
+const QUADRANTS: [(u64, u64); 4] = [(0, 0), (1, 0), (0, 1), (1, 1)];
+
+struct Macrocell {
+    // Nodes are numbered from 1, so node `i` is stored at `i - 1`
+    nodes: Vec<Node>,
+    bounds: Vec<Bounds>,
+    rule: Option<rules::Rule>
+}
+
+fn leaf(line: &str) -> anyhow::Result<Node> {
+    let mut cells = Vec::new();
+    let (mut x, mut y) = (0, 0);
+    for cell in line.chars() {
+        if cell != '$' && (x >= 8 || y >= 8) {
+            anyhow::bail!("'{}' reaches past the 8x8 leaf", line);
+        }
+
+        match cell {
+            '.' => x += 1,
+            '*' => {
+                cells.push((x, y));
+                x += 1;
+            },
+            '$' => {
+                x = 0;
+                y += 1;
+            },
+            _ => anyhow::bail!("'{}' is not a valid leaf cell", cell)
+        }
+    }
+
+    anyhow::Ok(Node::Leaf(cells))
+}
+
+fn parse_macrocell(macrocell: &str) -> anyhow::Result<Macrocell> {
+    let mut lines = macrocell.lines().map(str::trim);
+    if !lines.next().is_some_and(|line| line.starts_with("[M2]")) {
+        anyhow::bail!("macrocell files must start with '[M2]'");
+    }
+
+    let mut tree = Macrocell { nodes: Vec::new(), bounds: Vec::new(), rule: None };
+    for line in lines.filter(|line| !line.is_empty()) {
+        if let Some(rulestring) = line.strip_prefix("#R") {
+            tree.rule = pattern::rule(rulestring)?;
+            continue;
+        }
+
+        if line.starts_with('#') {
+            continue;
+        }
+
+        let node = if line.starts_with(['.', '*', '$']) {
+            leaf(line)?
+        } else {
+            let values = line
+                .split_whitespace()
+                .map(|value| value.parse::<u64>())
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let (level, children) = match values[..] {
+                [level, a, b, c, d] => (level as u32, [a, b, c, d]),
+                _ => anyhow::bail!("'{}' is not a valid node", line)
+            };
+
+            // Sizes are kept in a `u64`
+            if !(1..64).contains(&level) {
+                anyhow::bail!("level {} is out of range", level);
+            }
+
+            if level == 1 {
+                Node::States(children.map(|state| state as u32))
+            } else {
+                for &child in &children {
+                    let valid = match tree.nodes.get((child as usize).wrapping_sub(1)) {
+                        _ if child == 0 => true,
+                        Some(child) => child.level() == level - 1,
+                        None => false
+                    };
+
+                    if !valid {
+                        anyhow::bail!("node {} can't be a child of a level {} node", child, level);
+                    }
+                }
+
+                Node::Branch(level, children.map(|child| child as usize))
+            }
+        };
+
+        let bounds = match &node {
+            Node::Leaf(cells) => cells
+                .iter()
+                .fold(None, |bounds, &(x, y)| union(bounds, point(x, y))),
+            Node::States(states) => states
+                .iter()
+                .zip(QUADRANTS)
+                .filter(|(&state, _)| state != 0)
+                .fold(None, |bounds, (_, (x, y))| union(bounds, point(x, y))),
+            &Node::Branch(level, children) => {
+                let half = 1 << (level - 1);
+                children
+                    .iter()
+                    .zip(QUADRANTS)
+                    .filter(|(&child, _)| child != 0)
+                    .fold(None, |bounds, (&child, (qx, qy))| {
+                        let child = tree.bounds[child - 1]
+                            .map(|(min, max)| (
+                                (min.0 + qx * half, min.1 + qy * half),
+                                (max.0 + qx * half, max.1 + qy * half)
+                            ));
+
+                        union(bounds, child)
+                    } )
+            }
+        };
+
+        tree.nodes.push(node);
+        tree.bounds.push(bounds);
+    }
+
+    anyhow::Ok(tree)
+}
+
+impl Macrocell {
+    // Writes every living cell under `node` into the automata,
+    // where `corner` is the node's position relative to the pattern's bounds
+    fn expand(&self, automata: &mut Automata, node: usize, corner: (i64, i64)) {
+        if node == 0 || self.bounds[node - 1].is_none() {
+            return;
+        }
+
+        let mut set = |(x, y): (u64, u64), state: u32| {
+            automata[Point2::new((corner.0 + x as i64) as u32, (corner.1 + y as i64) as u32)] = state;
+        };
+
+        match &self.nodes[node - 1] {
+            Node::Leaf(cells) => cells.iter().for_each(|&cell| set(cell, 1)),
+            Node::States(states) => states
+                .iter()
+                .zip(QUADRANTS)
+                .filter(|(&state, _)| state != 0)
+                .for_each(|(&state, cell)| set(cell, state)),
+            &Node::Branch(level, children) => {
+                let half = 1i64 << (level - 1);
+                for (child, (qx, qy)) in children.into_iter().zip(QUADRANTS) {
+                    self.expand(automata, child, (corner.0 + qx as i64 * half, corner.1 + qy as i64 * half));
+                }
+            }
+        }
+    }
+}
+
+// Expands a Golly macrocell file into an automata just big enough to hold its living cells
+// Fails if that takes more than `budget` cells, or more than a storage buffer holds on a device with `limits`
+// The rule named in the file is returned alongside it
+pub fn automata_from_macrocell<C: Into<borrow::Cow<'static, str>>>(
+    file: C,
+    budget: u64,
+    limits: &wgpu::Limits
+) -> anyhow::Result<(Automata, Option<rules::Rule>)> {
+    expand_macrocell(&fs::read_to_string(&*file.into())?, budget, limits)
+}
+
+fn expand_macrocell(
+    macrocell: &str,
+    budget: u64,
+    limits: &wgpu::Limits
+) -> anyhow::Result<(Automata, Option<rules::Rule>)> {
+    let tree = parse_macrocell(macrocell)?;
+
+    // The last node is the root of the tree
+    let (min, max) = tree.bounds
+        .last()
+        .copied()
+        .flatten()
+        .ok_or_else(|| anyhow::anyhow!("the pattern has no living cells"))?;
+
+    let (width, height) = (max.0 - min.0 + 1, max.1 - min.1 + 1);
+    let cells = width.checked_mul(height).filter(|_| width <= u32::MAX as u64 && height <= u32::MAX as u64);
+
+    let limit = super::storage_buffer_cells(limits);
+    match cells {
+        Some(cells) if cells > budget => anyhow::bail!(
+            "a {}x{} pattern needs {} cells, past the budget of {}", width, height, cells, budget
+        ),
+        Some(cells) if cells <= limit => {  },
+        _ => anyhow::bail!(
+            "a {}x{} pattern is past the {} cells a storage buffer can hold", width, height, limit
+        )
+    }
+
+    let mut automata = Automata::new((width as u32, height as u32).into());
+    tree.expand(&mut automata, tree.nodes.len(), (-(min.0 as i64), -(min.1 as i64)));
+
+    anyhow::Ok((automata, tree.rule))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A leaf with a cell in each corner, as the only node
+    const CORNERS: &str = "[M2]\n*......*$$$$$$$*......*$\n";
+
+    fn expand(macrocell: &str) -> anyhow::Result<(Automata, Option<rules::Rule>)> {
+        expand_macrocell(macrocell, u64::MAX, &wgpu::Limits::default())
+    }
+
+    #[test]
+    fn expands_two_state_leaves() {
+        // Two leaves under a level 4 node, in its top-left and bottom-right quadrants
+        let (automata, rule) = expand("[M2]\n#R B36/S23\n*$.*$\n*$\n4 1 0 0 2\n").unwrap();
+
+        assert_eq!((automata.size.width, automata.size.height), (9, 9));
+        assert_eq!(rule.map(|rule| rule.to_string()), Some("B36/S23".to_string()));
+
+        let living = automata.data.iter().enumerate().filter(|(_, &state)| state != 0).map(|(i, _)| i).collect::<Vec<_>>();
+        assert_eq!(living, [0, 9 + 1, 8 * 9 + 8]);
+    }
+
+    #[test]
+    fn expands_multi_state_nodes() {
+        // The same level 1 node in the top-right and bottom-left of a level 2 node
+        let (automata, _) = expand("[M2]\n1 0 2 3 0\n2 0 1 1 0\n").unwrap();
+
+        assert_eq!((automata.size.width, automata.size.height), (4, 4));
+        assert_eq!(automata.data, [
+            0, 0, 0, 2,
+            0, 0, 3, 0,
+            0, 2, 0, 0,
+            3, 0, 0, 0
+        ]);
+    }
+
+    #[test]
+    fn rules() {
+        assert!(expand("[M2]\n#R WireWorld\n*$\n").unwrap().1.is_none());
+        assert!(expand("[M2]\n#R B3/S2x\n*$\n").is_err());
+    }
+
+    #[test]
+    fn refuses_patterns_past_the_budget() {
+        let limits = wgpu::Limits::default();
+
+        assert!(expand_macrocell(CORNERS, 64, &limits).is_ok());
+        assert_eq!(
+            expand_macrocell(CORNERS, 63, &limits).err().unwrap().to_string(),
+            "a 8x8 pattern needs 64 cells, past the budget of 63"
+        );
+    }
+
+    #[test]
+    fn refuses_patterns_past_the_storage_buffer() {
+        let limits = |cells: u32| wgpu::Limits { max_storage_buffer_binding_size: cells * 4, ..Default::default() };
+
+        assert!(expand_macrocell(CORNERS, u64::MAX, &limits(64)).is_ok());
+        assert_eq!(
+            expand_macrocell(CORNERS, u64::MAX, &limits(63)).err().unwrap().to_string(),
+            "a 8x8 pattern is past the 63 cells a storage buffer can hold"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_nodes() {
+        // A child defined after its parent, a child that doesn't exist, and a child of the wrong level
+        assert!(expand("[M2]\n4 2 0 0 0\n*$\n").is_err());
+        assert!(expand("[M2]\n*$\n4 7 0 0 0\n").is_err());
+        assert!(expand("[M2]\n*$\n5 1 0 0 0\n").is_err());
+        assert!(expand("[M2]\n*$\n4 99999999999999999999 0 0 0\n").is_err());
+
+        assert!(expand("[M2]\n0 1 0 0 0\n").is_err());
+        assert!(expand("[M2]\n64 0 0 0 0\n").is_err());
+        assert!(expand("[M2]\n4 0 0 0\n").is_err());
+        assert!(expand("[M2]\n*........$\n").is_err());
+        assert!(expand("*$\n").is_err());
+        assert!(expand("[M2]\n4 0 0 0 0\n").is_err());
+    }
+}
@@ -0,0 +1,640 @@
+use std::{
+    borrow,
+    ops::{ Index, IndexMut }
+};
+
+use winit::dpi;
+use cgmath::Point2;
+
+use crate::{
+    Neighborhood,
+    Topology,
+    gather_order
+};
+
+#[cfg(feature = "random")]
+use rand::seq;
+
+#[cfg(feature = "from_image")]
+use std::collections;
+
+#[cfg(feature = "from_image")]
+use crate::color;
+
+mod pattern;
+pub use pattern::Pattern;
+
+mod rle;
+pub use rle::{ pattern_from_rle, automata_from_rle };
+
+mod plaintext;
+pub use plaintext::pattern_from_cells;
+
+mod life;
+pub use life::{ pattern_from_life_105, pattern_from_life_106 };
+
+pub mod netpbm;
+
+mod macrocell;
+pub use macrocell::automata_from_macrocell;
+
+pub mod grid;
+pub use grid::automata_from_grid;
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Size {
+    pub width: u32,
+    pub height: u32,
+}
+
+// Helpful conversions from...
+impl From<(u32, u32)> for Size {
+    fn from(data: (u32, u32)) -> Self {
+        Self { width: data.0, height: data.1 }
+    }
+}
+
+// ...and to image dimensions
+impl From<Size> for (u32, u32) {
+    fn from(data: Size) -> Self {
+        (data.width, data.height)
+    }
+}
+
+// Similar conversions from the rendering engine's unit size...
+impl From<dpi::Size> for Size {
+    fn from(data: dpi::Size) -> Self {
+        let ds = data.to_physical(1.0);
+        let (width, height) = (ds.width, ds.height);
+
+        Self { width, height }
+    }
+}
+
+// ...and to it
+impl From<Size> for dpi::Size {
+    fn from(data: Size) -> Self {
+        let (width, height) = (data.width, data.height);
+        dpi::Size::Physical(dpi::PhysicalSize { width, height } )
+    }
+}
+
+#[derive(Clone)]
+pub struct Automata {
+    pub(crate) data: Vec<u32>,
+    pub(crate) size: Size
+}
+
+impl Index<Point2<u32>> for Automata {
+    type Output = u32;
+
+    fn index(&self, index: Point2<u32>) -> &Self::Output {
+        &self.data[(index.x + index.y * self.size.width) as usize]
+    }
+}
+
+impl IndexMut<Point2<u32>> for Automata {
+    fn index_mut(&mut self, index: Point2<u32>) -> &mut Self::Output {
+        &mut self.data[(index.x + index.y * self.size.width) as usize]
+    }
+}
+
+// A hexagon on a hexagonal grid, in axial coordinates:
+// `q` increases to the right and `r` increases downwards, leaning down and to the left
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Hex {
+    pub q: i32,
+    pub r: i32
+}
+
+impl Hex {
+    pub fn new(q: i32, r: i32) -> Self {
+        Self { q, r }
+    }
+
+    // The column and row the hexagon is stored at, where every odd row is shifted right by half a cell
+    pub fn offset(&self) -> Point2<i32> {
+        Point2::new(self.q + (self.r - (self.r & 1)) / 2, self.r)
+    }
+}
+
+impl From<Point2<u32>> for Hex {
+    fn from(cell: Point2<u32>) -> Self {
+        let (x, y) = (cell.x as i32, cell.y as i32);
+        Self { q: x - (y - (y & 1)) / 2, r: y }
+    }
+}
+
+// A cell picked out by its column and row on a square grid, or as a hexagon on a hexagonal one
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Cell {
+    Square(Point2<u32>),
+    Hex(Hex)
+}
+
+impl Cell {
+    // The column and row the cell is stored at, if it lies within a field of `size`
+    pub fn position(&self, size: Size) -> Option<Point2<u32>> {
+        let (x, y) = match *self {
+            Cell::Square(cell) => (cell.x as i64, cell.y as i64),
+            Cell::Hex(hex) => {
+                let offset = hex.offset();
+                (offset.x as i64, offset.y as i64)
+            }
+        };
+
+        let inside = (0..size.width as i64).contains(&x) && (0..size.height as i64).contains(&y);
+        inside.then(|| Point2::new(x as u32, y as u32))
+    }
+}
+
+impl From<Point2<u32>> for Cell {
+    fn from(cell: Point2<u32>) -> Self {
+        Cell::Square(cell)
+    }
+}
+
+impl From<Hex> for Cell {
+    fn from(hex: Hex) -> Self {
+        Cell::Hex(hex)
+    }
+}
+
+impl Index<Hex> for Automata {
+    type Output = u32;
+
+    fn index(&self, index: Hex) -> &Self::Output {
+        let cell = Cell::Hex(index)
+            .position(self.size)
+            .expect("the hexagon lies outside the field");
+
+        &self[cell]
+    }
+}
+
+impl IndexMut<Hex> for Automata {
+    fn index_mut(&mut self, index: Hex) -> &mut Self::Output {
+        let cell = Cell::Hex(index)
+            .position(self.size)
+            .expect("the hexagon lies outside the field");
+
+        &mut self[cell]
+    }
+}
+
+impl Automata {
+    pub fn new(size: Size) -> Self {
+        Self { data: vec![0; (size.width * size.height) as usize], size }
+    }
+
+    pub fn size(&self) -> Size {
+        self.size
+    }
+}
+
+// The most cells a single storage buffer can hold on a device with these limits,
+// such as `adapter.limits()` or `device.limits()`
+pub fn storage_buffer_cells(limits: &wgpu::Limits) -> u64 {
+    limits.max_storage_buffer_binding_size as u64 / 4
+}
+
+#[cfg(feature = "random")]
+pub fn random_automata(
+    size: Size, 
+    states: &[u32]
+) -> Automata {
+    random_automata_with_padding(size, states, 0)
+}
+
+#[cfg(feature = "random")]
+pub fn random_automata_with_padding(
+    size: Size, 
+    states: &[u32],
+    padding: u32
+) -> Automata {
+    let mut prng = rand::thread_rng();
+
+    let mut automata = Automata::new(size);
+    for x in padding..(size.width - padding) {
+        for y in padding..(size.height - padding) {
+            automata[Point2::new(x, y)] = *seq::SliceRandom::choose(states, &mut prng).unwrap();
+        }
+    }
+    
+    automata
+}
+
+// Reads the cells from a PBM or PGM file, using each sample as a cell's state
+pub fn automata_from_pgm<C: Into<borrow::Cow<'static, str>>>(file: C) -> anyhow::Result<Automata> {
+    let image = netpbm::Image::open(&*file.into())?;
+    if image.format.channels() != 1 {
+        anyhow::bail!("a {:?} pixmap has no single state per pixel, use `automata_from_image`", image.format);
+    }
+
+    let mut automata = Automata::new(image.size());
+    automata.data = image.samples.into_iter().map(u32::from).collect();
+
+    anyhow::Ok(automata)
+}
+
+// Reads the non-zero pixels of a PGM mask with odd dimensions, apart from the center,
+// as offsets from the center paired with their values
+// The offsets are gathered in the same order as the radius-based neighborhoods
+fn mask_from_pgm<C: Into<borrow::Cow<'static, str>>>(file: C) -> anyhow::Result<Vec<((i32, i32), u32)>> {
+    let mask = automata_from_pgm(file)?;
+    if mask.size.width % 2 == 0 || mask.size.height % 2 == 0 {
+        anyhow::bail!(
+            "a {}x{} mask has no center cell, both dimensions must be odd",
+            mask.size.width, mask.size.height
+        );
+    }
+
+    let center = ((mask.size.width / 2) as i32, (mask.size.height / 2) as i32);
+    let value = |(dx, dy): (i32, i32)| {
+        let (x, y) = (center.0 + dx, center.1 + dy);
+        if x < 0 || y < 0 || x >= mask.size.width as i32 || y >= mask.size.height as i32 {
+            return 0;
+        }
+
+        mask[Point2::new(x as u32, y as u32)]
+    };
+
+    let offsets = gather_order(center.0.max(center.1), Topology::Square, |offset| value(offset) != 0);
+
+    anyhow::Ok(offsets.into_iter().map(|offset| (offset, value(offset))).collect())
+}
+
+// Reads a custom neighborhood from a PGM mask, see `mask_from_pgm`
+pub fn neighborhood_from_pgm<C: Into<borrow::Cow<'static, str>>>(file: C) -> anyhow::Result<Neighborhood> {
+    let mask = mask_from_pgm(file)?;
+    anyhow::Ok(Neighborhood::Custom(mask.into_iter().map(|(offset, _)| offset).collect()))
+}
+
+// Reads a weighted neighborhood from a PGM mask, see `mask_from_pgm`
+// Each pixel's value is used as the weight of its offset
+pub fn weighted_neighborhood_from_pgm<C: Into<borrow::Cow<'static, str>>>(file: C) -> anyhow::Result<Neighborhood> {
+    let mask = mask_from_pgm(file)?;
+    anyhow::Ok(Neighborhood::Weighted(mask.into_iter().map(|(offset, weight)| (offset, weight as f32)).collect()))
+}
+
+// Reads an image that starts with a legend row like the one `Automata::save_image` writes:
+// at least two different colors, the last of them repeated to the end of the row, and no other colors below it
+// Each color's state is its place in the legend, and the legend itself is left out
+#[cfg(feature = "from_image")]
+fn automata_from_legend(image: &image::RgbImage) -> Option<Automata> {
+    let (width, height) = image.dimensions();
+    if height < 2 {
+        return None;
+    }
+
+    let mut states = collections::HashMap::new();
+    let mut repeating = false;
+    for x in 0..width {
+        let color = image[(x, 0)].0;
+        if !states.contains_key(&color) && !repeating {
+            states.insert(color, states.len() as u32);
+        } else if x > 0 && image[(x - 1, 0)].0 == color {
+            repeating = true;
+        } else {
+            return None;
+        }
+    }
+
+    if states.len() < 2 {
+        return None;
+    }
+
+    let mut automata = Automata::new((width, height - 1).into());
+    for (state, pixel) in automata.data.iter_mut().zip(image.pixels().skip(width as usize)) {
+        *state = *states.get(&pixel.0)?;
+    }
+
+    Some(automata)
+}
+
+// Images with a legend row, such as those saved by `Automata::save_image`, keep the legend's order of colors
+#[cfg(feature = "from_image")]
+pub fn automata_from_image<C: Into<borrow::Cow<'static, str>>>(file: C) -> anyhow::Result<Automata> {
+    let image = image::open(&*file.into())?;
+    if let Some(automata) = automata_from_legend(&image.to_rgb8()) {
+        return anyhow::Ok(automata);
+    }
+
+    let image = image.to_luma8();
+
+    // Create the new automata object
+    let mut automata = Automata::new(image.dimensions().into());
+
+    let mut states = collections::HashMap::new();
+    for x in 0..automata.size.width {
+        for y in 0..automata.size.height {
+            let curr_state;
+            match states.get(&image[(x, y)]) {
+                Some(&state) => curr_state = state,
+                None => { 
+                    curr_state = states.len() as u32;
+                    states.insert(image[(x, y)], curr_state);
+                }
+            }
+
+            automata[Point2::new(x, y)] = curr_state;
+        }
+    }
+
+    anyhow::Ok(automata)
+}
+
+// What to do with a color the palette doesn't contain
+#[cfg(feature = "from_image")]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Unmapped {
+    Error,
+    // The closest color in the palette, by distance in RGB
+    Nearest
+}
+
+// Reads each pixel's state from an explicit palette, so states don't depend on where colors appear
+#[cfg(feature = "from_image")]
+pub fn automata_from_image_with_palette<C: Into<borrow::Cow<'static, str>>>(
+    file: C,
+    palette: &collections::HashMap<[u8; 3], u32>,
+    unmapped: Unmapped
+) -> anyhow::Result<Automata> {
+    let image = image::open(&*file.into())?.to_rgb8();
+
+    // Sorted, so that ties between equally near colors always go the same way
+    let mut entries = palette.iter().map(|(&color, &state)| (state, color)).collect::<Vec<_>>();
+    entries.sort_unstable();
+
+    let nearest = |color: [u8; 3]| entries
+        .iter()
+        .min_by_key(|(_, entry)| (0..3)
+            .map(|i| (color[i] as i32 - entry[i] as i32).pow(2))
+            .sum::<i32>())
+        .map(|&(state, _)| state);
+
+    let mut automata = Automata::new(image.dimensions().into());
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let state = match (palette.get(&pixel.0), unmapped) {
+            (Some(&state), _) => state,
+            (None, Unmapped::Nearest) if !entries.is_empty() => nearest(pixel.0).unwrap(),
+            _ => anyhow::bail!(
+                "the pixel at ({}, {}) is #{:02x}{:02x}{:02x}, which isn't in the palette",
+                x, y, pixel[0], pixel[1], pixel[2]
+            )
+        };
+
+        automata[Point2::new(x, y)] = state;
+    }
+
+    anyhow::Ok(automata)
+}
+
+// Reads a palette with a `state red green blue` line for each color
+// Everything after a `#` is a comment
+#[cfg(feature = "from_image")]
+pub fn palette_from_file<C: Into<borrow::Cow<'static, str>>>(
+    file: C
+) -> anyhow::Result<collections::HashMap<[u8; 3], u32>> {
+    let mut palette = collections::HashMap::new();
+    for (i, line) in std::fs::read_to_string(&*file.into())?.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let values = line.split_whitespace().collect::<Vec<_>>();
+        let entry = match values[..] {
+            [state, r, g, b] => state
+                .parse::<u32>()
+                .ok()
+                .zip([r, g, b].map(|c| c.parse::<u8>().ok()).into_iter().collect::<Option<Vec<_>>>()),
+            _ => None
+        };
+
+        match entry {
+            Some((state, color)) => {
+                if let Some(other) = palette.insert([color[0], color[1], color[2]], state) {
+                    anyhow::bail!("line {} maps a color to {} that's already mapped to {}", i + 1, state, other);
+                }
+            },
+            None => anyhow::bail!("line {} is not a `state red green blue` entry", i + 1)
+        }
+    }
+
+    anyhow::Ok(palette)
+}
+
+impl Automata {
+    // Writes each cell's state as a sample of a plain (P2) or raw (P5) graymap
+    pub fn save_pgm<C: Into<borrow::Cow<'static, str>>>(
+        &self,
+        file: C,
+        format: netpbm::Format
+    ) -> anyhow::Result<()> {
+        if !matches!(format, netpbm::Format::P2 | netpbm::Format::P5) {
+            anyhow::bail!("{:?} is not a graymap format", format);
+        }
+
+        let maxval = self.data.iter().copied().max().unwrap_or(0).max(1);
+        if maxval > u16::MAX as u32 {
+            anyhow::bail!("state {} is past the largest sample a graymap can hold", maxval);
+        }
+
+        let image = netpbm::Image {
+            format,
+            width: self.size.width,
+            height: self.size.height,
+            maxval: maxval as u16,
+            samples: self.data.iter().map(|&state| state as u16).collect()
+        };
+
+        image.save(&*file.into())
+    }
+
+    // Draws each cell in the color `coloring` gives its state
+    // With a legend, a row holding the color of every state in order is added on top,
+    // so that `automata_from_image` gives back the same states
+    // The legend always holds at least states 0 and 1, so it can't be mistaken for a plain single-color image
+    #[cfg(feature = "from_image")]
+    pub fn save_image<C: Into<borrow::Cow<'static, str>>>(
+        &self,
+        file: C,
+        coloring: &[color::Coloring],
+        legend: bool
+    ) -> anyhow::Result<()> {
+        let pixel = |state: u32| image::Rgb(
+            color::get_color(coloring, state).map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+        );
+
+        let skip = u32::from(legend);
+        let mut image = image::RgbImage::new(self.size.width, self.size.height + skip);
+
+        if legend {
+            let states = self.data.iter().copied().max().unwrap_or(0).max(1) + 1;
+            if states > self.size.width {
+                anyhow::bail!("a legend for {} states doesn't fit in {} columns", states, self.size.width);
+            }
+
+            let colors = (0..states).map(pixel).collect::<Vec<_>>();
+            for (state, color) in colors.iter().enumerate() {
+                if let Some(other) = colors[..state].iter().position(|other| other == color) {
+                    anyhow::bail!("states {} and {} share a color, so they can't be told apart", other, state);
+                }
+            }
+
+            // Repeating the last color past the end of the legend adds no new states
+            for x in 0..self.size.width {
+                image.put_pixel(x, 0, colors[x.min(states - 1) as usize]);
+            }
+        }
+
+        for (i, &state) in self.data.iter().enumerate() {
+            let i = i as u32;
+            image.put_pixel(i % self.size.width, i / self.size.width + skip, pixel(state));
+        }
+
+        anyhow::Ok(image.save(&*file.into())?)
+    }
+}
+
+#[cfg(all(test, feature = "from_image"))]
+mod tests {
+    use super::*;
+
+    // A file of its own in the temp directory, so tests running in parallel don't collide
+    fn file(name: &str, extension: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("gridded_automata_{}_{}.{}", name, std::process::id(), extension))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn coloring() -> Vec<color::Coloring> {
+        vec![
+            color::map(0, [0.0, 0.0, 0.0]),
+            color::map(1, [1.0, 1.0, 1.0]),
+            color::map(2, [1.0, 0.0, 0.0]),
+            color::map(3, [0.0, 0.0, 1.0])
+        ]
+    }
+
+    #[test]
+    fn legend_round_trip() {
+        // The highest state comes first, so only the legend can put the states back in order
+        let mut automata = Automata::new((5, 3).into());
+        automata.data = vec![3, 0, 0, 1, 0, 0, 2, 2, 0, 0, 1, 0, 0, 0, 3];
+
+        let file = file("legend", "png");
+        automata.save_image(file.clone(), &coloring(), true).unwrap();
+        let loaded = automata_from_image(file.clone());
+        std::fs::remove_file(&file).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!((loaded.size.width, loaded.size.height), (5, 3));
+        assert_eq!(loaded.data, automata.data);
+    }
+
+    #[test]
+    fn legend_round_trip_of_an_empty_grid() {
+        let automata = Automata::new((3, 2).into());
+
+        let file = file("empty_legend", "png");
+        automata.save_image(file.clone(), &coloring(), true).unwrap();
+        let loaded = automata_from_image(file.clone());
+        std::fs::remove_file(&file).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!((loaded.size.width, loaded.size.height), (3, 2));
+        assert_eq!(loaded.data, automata.data);
+    }
+
+    #[test]
+    fn images_without_a_legend_keep_every_row() {
+        // The top row repeats a color after a new one, so it isn't a legend
+        let (a, b, c) = (image::Rgb([0, 0, 0]), image::Rgb([255, 255, 255]), image::Rgb([90, 90, 90]));
+        let mut image = image::RgbImage::new(3, 2);
+        for (x, y, pixel) in [(0, 0, a), (1, 0, b), (2, 0, a), (0, 1, c), (1, 1, b), (2, 1, c)] {
+            image.put_pixel(x, y, pixel);
+        }
+
+        let file = file("no_legend", "png");
+        image.save(&file).unwrap();
+        let loaded = automata_from_image(file.clone());
+        std::fs::remove_file(&file).unwrap();
+
+        // Numbered in the order each column is scanned from top to bottom
+        let loaded = loaded.unwrap();
+        assert_eq!((loaded.size.width, loaded.size.height), (3, 2));
+        assert_eq!(loaded.data, [0, 2, 0, 1, 2, 1]);
+    }
+
+    #[test]
+    fn legend_needs_room_and_distinct_colors() {
+        let mut automata = Automata::new((3, 1).into());
+        automata.data = vec![0, 1, 3];
+        assert!(automata.save_image(file("narrow", "png"), &coloring(), true).is_err());
+
+        automata.data = vec![0, 1, 2];
+        let shared = [color::map(1, [1.0, 0.0, 0.0]), color::map(2, [1.0, 0.0, 0.0])];
+        assert!(automata.save_image(file("shared", "png"), &shared, true).is_err());
+    }
+
+    fn palette(name: &str, palette: &str) -> anyhow::Result<collections::HashMap<[u8; 3], u32>> {
+        let file = file(name, "palette");
+        std::fs::write(&file, palette).unwrap();
+        let palette = palette_from_file(file.clone());
+        std::fs::remove_file(&file).unwrap();
+
+        palette
+    }
+
+    // Wire World's head, tail and conductor, plus a slightly off conductor drawn by hand
+    fn circuit(name: &str, palette: &collections::HashMap<[u8; 3], u32>, unmapped: Unmapped) -> anyhow::Result<Automata> {
+        let mut image = image::RgbImage::new(4, 1);
+        for (x, color) in [[0, 0, 0], [0, 0, 255], [255, 0, 0], [250, 240, 10]].into_iter().enumerate() {
+            image.put_pixel(x as u32, 0, image::Rgb(color));
+        }
+
+        let file = file(name, "png");
+        image.save(&file).unwrap();
+        let automata = automata_from_image_with_palette(file.clone(), palette, unmapped);
+        std::fs::remove_file(&file).unwrap();
+
+        automata
+    }
+
+    #[test]
+    fn palette_skips_comments() {
+        let palette = palette("comments", "# Wire World\n\n0 0 0 0\n1 0 0 255 # head\n2 255 0 0\n  3 255 255 0\n").unwrap();
+
+        assert_eq!(palette.len(), 4);
+        assert_eq!(palette[&[0, 0, 255]], 1);
+        assert_eq!(palette[&[255, 255, 0]], 3);
+    }
+
+    #[test]
+    fn palette_rejects_bad_lines() {
+        assert!(palette("duplicate", "1 0 0 255\n2 0 0 255\n").is_err());
+        assert!(palette("past_a_byte", "1 0 0 256\n").is_err());
+        assert!(palette("negative", "-1 0 0 0\n").is_err());
+        assert!(palette("short", "1 0 0\n").is_err());
+        assert!(palette("long", "1 0 0 0 0\n").is_err());
+    }
+
+    #[test]
+    fn unmapped_colors_are_an_error() {
+        let palette = palette("error", "0 0 0 0\n1 0 0 255\n2 255 0 0\n3 255 255 0\n").unwrap();
+        let error = circuit("error", &palette, Unmapped::Error).err().unwrap().to_string();
+
+        assert_eq!(error, "the pixel at (3, 0) is #faf00a, which isn't in the palette");
+    }
+
+    #[test]
+    fn unmapped_colors_go_to_the_nearest_entry() {
+        let palette = palette("nearest", "0 0 0 0\n1 0 0 255\n2 255 0 0\n3 255 255 0\n").unwrap();
+
+        assert_eq!(circuit("nearest", &palette, Unmapped::Nearest).unwrap().data, [0, 1, 2, 3]);
+    }
+}
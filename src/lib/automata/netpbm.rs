@@ -0,0 +1,420 @@
+use std::{
+    fmt,
+    fs,
+    path
+};
+
+use super::Size;
+
+// The six Netpbm formats, by their magic numbers
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    // Plain (ASCII) bitmap, graymap and pixmap
+    P1, P2, P3,
+    // Raw (binary) bitmap, graymap and pixmap
+    P4, P5, P6
+}
+
+impl Format {
+    // Pixmaps have a red, green and blue sample per pixel
+    pub fn channels(&self) -> usize {
+        match self {
+            Format::P3 | Format::P6 => 3,
+            _ => 1
+        }
+    }
+
+    fn is_bitmap(&self) -> bool {
+        matches!(self, Format::P1 | Format::P4)
+    }
+
+    fn is_plain(&self) -> bool {
+        matches!(self, Format::P1 | Format::P2 | Format::P3)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ErrorKind {
+    // The file doesn't start with `P1` through `P6`
+    MagicNumber,
+    // The file ended before the header or raster was complete
+    UnexpectedEof,
+    // A header field or plain sample isn't a decimal number
+    InvalidNumber,
+    // The maxval isn't between 1 and 65535
+    Maxval(u32),
+    // A sample is larger than the maxval
+    Sample(u32),
+    // A plain bitmap sample isn't `0` or `1`
+    Bit(char),
+    // The width and height don't describe a raster that fits in memory
+    Dimensions
+}
+
+// Where in the file decoding failed, and why
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Error {
+    pub kind: ErrorKind,
+    // Lines are counted from 1, bytes from 0
+    pub line: usize,
+    pub byte: usize
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ErrorKind::MagicNumber => write!(f, "expected a magic number between P1 and P6")?,
+            ErrorKind::UnexpectedEof => write!(f, "unexpected end of file")?,
+            ErrorKind::InvalidNumber => write!(f, "expected a decimal number")?,
+            ErrorKind::Maxval(maxval) => write!(f, "maxval {} is not between 1 and 65535", maxval)?,
+            ErrorKind::Sample(sample) => write!(f, "sample {} is larger than the maxval", sample)?,
+            ErrorKind::Bit(bit) => write!(f, "'{}' is not a bitmap sample", bit)?,
+            ErrorKind::Dimensions => write!(f, "the image's dimensions are too large")?
+        }
+
+        write!(f, " (line {}, byte {})", self.line, self.byte)
+    }
+}
+
+impl std::error::Error for Error {  }
+
+// A decoded image, with `format.channels()` samples per pixel in row-major order
+// Bitmaps use 1 for black and 0 for white, like the files themselves
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Image {
+    pub format: Format,
+    pub width: u32,
+    pub height: u32,
+    pub maxval: u16,
+    pub samples: Vec<u16>
+}
+
+// Walks the file one byte at a time, keeping track of the position for errors
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize
+}
+
+impl Reader<'_> {
+    fn error(&self, kind: ErrorKind) -> Error {
+        let line = self.bytes[..self.position.min(self.bytes.len())]
+            .iter()
+            .filter(|&&byte| byte == b'\n')
+            .count() + 1;
+
+        Error { kind, line, byte: self.position }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    // Skips whitespace along with comments, which run from `#` to the end of the line
+    fn skip(&mut self) {
+        while let Some(byte) = self.peek() {
+            match byte {
+                b'#' => while !matches!(self.peek(), Some(b'\n' | b'\r') | None) {
+                    self.position += 1;
+                },
+                _ if byte.is_ascii_whitespace() => self.position += 1,
+                _ => break
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<u32, Error> {
+        self.skip();
+
+        let start = self.position;
+        let mut value: u32 = 0;
+        while let Some(digit @ b'0'..=b'9') = self.peek() {
+            value = value
+                .checked_mul(10)
+                .and_then(|value| value.checked_add((digit - b'0') as u32))
+                .ok_or_else(|| self.error(ErrorKind::InvalidNumber))?;
+
+            self.position += 1;
+        }
+
+        match self.peek() {
+            None if start == self.position => Err(self.error(ErrorKind::UnexpectedEof)),
+            _ if start == self.position => Err(self.error(ErrorKind::InvalidNumber)),
+            _ => Ok(value)
+        }
+    }
+
+    // Plain bitmaps don't need whitespace between samples
+    fn bit(&mut self) -> Result<u16, Error> {
+        self.skip();
+
+        match self.peek() {
+            Some(b'0') => { self.position += 1; Ok(0) },
+            Some(b'1') => { self.position += 1; Ok(1) },
+            Some(byte) => Err(self.error(ErrorKind::Bit(byte as char))),
+            None => Err(self.error(ErrorKind::UnexpectedEof))
+        }
+    }
+
+    fn byte(&mut self) -> Result<u8, Error> {
+        let byte = self.peek().ok_or_else(|| self.error(ErrorKind::UnexpectedEof))?;
+        self.position += 1;
+
+        Ok(byte)
+    }
+}
+
+impl Image {
+    // Decodes the first image in a Netpbm file
+    pub fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader { bytes, position: 0 };
+
+        let format = match (reader.byte(), reader.byte()) {
+            (Ok(b'P'), Ok(b'1')) => Format::P1,
+            (Ok(b'P'), Ok(b'2')) => Format::P2,
+            (Ok(b'P'), Ok(b'3')) => Format::P3,
+            (Ok(b'P'), Ok(b'4')) => Format::P4,
+            (Ok(b'P'), Ok(b'5')) => Format::P5,
+            (Ok(b'P'), Ok(b'6')) => Format::P6,
+            _ => return Err(Error { kind: ErrorKind::MagicNumber, line: 1, byte: 0 })
+        };
+
+        let width = reader.number()?;
+        let height = reader.number()?;
+
+        let maxval = if format.is_bitmap() {
+            1
+        } else {
+            reader.skip();
+
+            let start = reader.position;
+            let maxval = reader.number()?;
+            if !(1..=u16::MAX as u32).contains(&maxval) {
+                reader.position = start;
+                return Err(reader.error(ErrorKind::Maxval(maxval)));
+            }
+
+            maxval as u16
+        };
+
+        let count = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|pixels| pixels.checked_mul(format.channels()))
+            .ok_or_else(|| reader.error(ErrorKind::Dimensions))?;
+
+        // A single whitespace byte separates the header from a raw raster,
+        // or a comment straight after the last field, which ends with the line
+        if !format.is_plain() {
+            match reader.byte()? {
+                byte if byte.is_ascii_whitespace() => {  },
+                b'#' => loop {
+                    match reader.byte()? {
+                        b'\n' => break,
+                        // A CRLF line ending is a single line break
+                        b'\r' => {
+                            if reader.peek() == Some(b'\n') {
+                                reader.position += 1;
+                            }
+
+                            break;
+                        },
+                        _ => {  }
+                    }
+                },
+                _ => {
+                    reader.position -= 1;
+                    return Err(reader.error(ErrorKind::InvalidNumber));
+                }
+            }
+        }
+
+        // Don't trust the header with the allocation, a raw sample takes at least a bit
+        let mut samples = Vec::with_capacity(count.min(bytes.len() * 8));
+        match format {
+            Format::P1 => for _ in 0..count {
+                samples.push(reader.bit()?);
+            },
+            Format::P2 | Format::P3 => for _ in 0..count {
+                let start = reader.position;
+                let sample = reader.number()?;
+                if sample > maxval as u32 {
+                    reader.position = start;
+                    reader.skip();
+                    return Err(reader.error(ErrorKind::Sample(sample)));
+                }
+
+                samples.push(sample as u16);
+            },
+            // Each row of a raw bitmap is padded to a whole byte
+            Format::P4 => for _ in 0..height {
+                let mut byte = 0;
+                for x in 0..width {
+                    if x % 8 == 0 {
+                        byte = reader.byte()?;
+                    }
+
+                    samples.push(((byte >> (7 - x % 8)) & 1) as u16);
+                }
+            },
+            // Samples take two big-endian bytes when the maxval doesn't fit in one
+            Format::P5 | Format::P6 => for _ in 0..count {
+                let start = reader.position;
+                let sample = if maxval > u8::MAX as u16 {
+                    u16::from_be_bytes([reader.byte()?, reader.byte()?])
+                } else {
+                    reader.byte()? as u16
+                };
+
+                if sample > maxval {
+                    reader.position = start;
+                    return Err(reader.error(ErrorKind::Sample(sample as u32)));
+                }
+
+                samples.push(sample);
+            }
+        }
+
+        Ok(Self { format, width, height, maxval, samples })
+    }
+
+    // Encodes the image in its format, wrapping plain rasters at 70 characters a line
+    pub fn encode(&self) -> Vec<u8> {
+        let magic = match self.format {
+            Format::P1 => "P1", Format::P2 => "P2", Format::P3 => "P3",
+            Format::P4 => "P4", Format::P5 => "P5", Format::P6 => "P6"
+        };
+
+        let mut bytes = if self.format.is_bitmap() {
+            format!("{}\n{} {}\n", magic, self.width, self.height)
+        } else {
+            format!("{}\n{} {}\n{}\n", magic, self.width, self.height, self.maxval)
+        }.into_bytes();
+
+        match self.format {
+            Format::P1 | Format::P2 | Format::P3 => {
+                let mut line = String::new();
+                for sample in &self.samples {
+                    let sample = sample.to_string();
+                    if !line.is_empty() && line.len() + sample.len() + 1 > 70 {
+                        bytes.extend(line.drain(..).chain(Some('\n')).collect::<String>().bytes());
+                    }
+
+                    if !line.is_empty() {
+                        line.push(' ');
+                    }
+
+                    line.push_str(&sample);
+                }
+
+                if !line.is_empty() {
+                    bytes.extend(line.bytes());
+                    bytes.push(b'\n');
+                }
+            },
+            Format::P4 => for row in self.samples.chunks(self.width.max(1) as usize) {
+                for bits in row.chunks(8) {
+                    let byte = bits
+                        .iter()
+                        .enumerate()
+                        .fold(0u8, |byte, (i, &bit)| byte | (u8::from(bit != 0) << (7 - i)));
+
+                    bytes.push(byte);
+                }
+            },
+            Format::P5 | Format::P6 => for &sample in &self.samples {
+                if self.maxval > u8::MAX as u16 {
+                    bytes.extend(sample.to_be_bytes());
+                } else {
+                    bytes.push(sample as u8);
+                }
+            }
+        }
+
+        bytes
+    }
+
+    pub fn open<P: AsRef<path::Path>>(file: P) -> anyhow::Result<Self> {
+        anyhow::Ok(Self::decode(&fs::read(file)?)?)
+    }
+
+    pub fn save<P: AsRef<path::Path>>(&self, file: P) -> anyhow::Result<()> {
+        anyhow::Ok(fs::write(file, self.encode())?)
+    }
+
+    pub fn size(&self) -> Size {
+        (self.width, self.height).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(format: Format, width: u32, height: u32, maxval: u16) {
+        let samples = (0..width * height * format.channels() as u32)
+            .map(|i| (i * 7919 % (maxval as u32 + 1)) as u16)
+            .collect();
+
+        let image = Image { format, width, height, maxval, samples };
+        assert_eq!(Image::decode(&image.encode()), Ok(image));
+    }
+
+    fn error(bytes: &[u8]) -> (ErrorKind, usize, usize) {
+        let error = Image::decode(bytes).unwrap_err();
+        (error.kind, error.line, error.byte)
+    }
+
+    #[test]
+    fn bitmaps_round_trip() {
+        // Rows that don't fill their last byte, and plain rows longer than a line
+        round_trip(Format::P1, 45, 3, 1);
+        round_trip(Format::P4, 13, 5, 1);
+    }
+
+    #[test]
+    fn graymaps_round_trip() {
+        round_trip(Format::P2, 9, 4, 3);
+        round_trip(Format::P2, 9, 4, u16::MAX);
+        round_trip(Format::P5, 9, 4, u8::MAX as u16);
+        round_trip(Format::P5, 9, 4, u16::MAX);
+    }
+
+    #[test]
+    fn pixmaps_round_trip() {
+        round_trip(Format::P3, 5, 2, 1000);
+        round_trip(Format::P6, 5, 2, 1000);
+    }
+
+    #[test]
+    fn sixteen_bit_samples_are_big_endian() {
+        let image = Image::decode(b"P5\n2 1\n65535\n\x01\x02\xff\xfe").unwrap();
+        assert_eq!(image.samples, [0x0102, 0xfffe]);
+    }
+
+    #[test]
+    fn comments_are_skipped_in_the_header() {
+        let image = Image::decode(b"P2\n# made by hand\n2 1 # a row\n3\n1 # first\n2\n").unwrap();
+        assert_eq!(image.samples, [1, 2]);
+
+        // Straight after the maxval, a comment stands in for the whitespace before the raster
+        let image = Image::decode(b"P5\n2 1\n255# raw samples follow\n\x07\x08").unwrap();
+        assert_eq!(image.samples, [7, 8]);
+
+        // Whichever line ending the comment has
+        let image = Image::decode(b"P5\r\n2 1\r\n255# raw samples follow\r\n\x07\x08").unwrap();
+        assert_eq!(image.samples, [7, 8]);
+
+        let image = Image::decode(b"P5\r2 1\r255# raw samples follow\r\x07\x08").unwrap();
+        assert_eq!(image.samples, [7, 8]);
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_byte() {
+        assert_eq!(error(b"P7\n1 1\n"), (ErrorKind::MagicNumber, 1, 0));
+        assert_eq!(error(b"P2\n1 1\n70000\n0\n"), (ErrorKind::Maxval(70000), 3, 7));
+        assert_eq!(error(b"P2\n2 2\n3\n0 1\n2 9\n"), (ErrorKind::Sample(9), 5, 15));
+        assert_eq!(error(b"P1\n2 1\n0 2\n"), (ErrorKind::Bit('2'), 3, 9));
+        assert_eq!(error(b"P2\nx 1\n"), (ErrorKind::InvalidNumber, 2, 3));
+        assert_eq!(error(b"P5\n2 1\n255\n\x01"), (ErrorKind::UnexpectedEof, 4, 12));
+        assert_eq!(error(b"P5\n1 1\n16\n\x11"), (ErrorKind::Sample(17), 4, 10));
+    }
+}
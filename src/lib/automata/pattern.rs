@@ -0,0 +1,146 @@
+use cgmath::Point2;
+
+use crate::rules;
+
+use super::{ Automata, Size };
+
+// Pattern files often name rules this library can't run, like Golly's `WireWorld`,
+// which are left out rather than failing the whole file
+// Anything written as a rulestring, with a `/` or as a Larger-than-Life rule, still has to parse
+pub(crate) fn rule(rulestring: &str) -> anyhow::Result<Option<rules::Rule>> {
+    let rulestring = rulestring.trim();
+    let larger_than_life = rulestring.starts_with(['R', 'r']) && rulestring.contains(',');
+    if !rulestring.contains('/') && !larger_than_life {
+        return anyhow::Ok(None);
+    }
+
+    anyhow::Ok(Some(rulestring.parse::<rules::Rule>()?))
+}
+
+// A sparse set of living cells, as read from a pattern file
+// Only non-zero cells are kept, relative to the pattern's top-left corner
+pub struct Pattern {
+    pub(crate) size: Size,
+    pub(crate) cells: Vec<(Point2<u32>, u32)>,
+    pub(crate) rule: Option<rules::Rule>
+}
+
+impl Pattern {
+    // Builds a pattern from coordinates that may reach past its top-left corner,
+    // shifting them back into view if they do
+    pub(crate) fn sparse(cells: Vec<((i32, i32), u32)>, rule: Option<rules::Rule>) -> Self {
+        let min = |axis: fn(&(i32, i32)) -> i32| cells
+            .iter()
+            .map(|(cell, _)| axis(cell))
+            .min()
+            .unwrap_or(0)
+            .min(0);
+
+        let (min_x, min_y) = (min(|cell| cell.0), min(|cell| cell.1));
+        let cells = cells
+            .into_iter()
+            .map(|((x, y), state)| (Point2::new((x - min_x) as u32, (y - min_y) as u32), state))
+            .collect::<Vec<_>>();
+
+        let width = cells.iter().map(|(cell, _)| cell.x + 1).max().unwrap_or(0);
+        let height = cells.iter().map(|(cell, _)| cell.y + 1).max().unwrap_or(0);
+
+        Self { size: (width, height).into(), cells, rule }
+    }
+
+    // Formats that only know living and dead cells can't hold anything else
+    pub(crate) fn two_state(&self) -> anyhow::Result<()> {
+        match self.cells.iter().find(|&&(_, state)| state > 1) {
+            Some((_, state)) => anyhow::bail!("state {} can't be written to a two-state format", state),
+            None => anyhow::Ok(())
+        }
+    }
+
+    // The pattern's bounding box
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    pub fn cells(&self) -> &[(Point2<u32>, u32)] {
+        &self.cells
+    }
+
+    // The rule the pattern was saved with, if the file named one
+    pub fn rule(&self) -> Option<&rules::Rule> {
+        self.rule.as_ref()
+    }
+
+    // Copies the pattern's cells into the automata, with its top-left corner at `offset`
+    // Cells the pattern leaves empty are left untouched
+    pub fn stamp(&self, automata: &mut Automata, offset: Point2<u32>) -> anyhow::Result<()> {
+        let fits = |extent: u32, offset: u32, limit: u32| offset
+            .checked_add(extent)
+            .is_some_and(|end| end <= limit);
+
+        if !fits(self.size.width, offset.x, automata.size.width)
+            || !fits(self.size.height, offset.y, automata.size.height) {
+            anyhow::bail!(
+                "a {}x{} pattern at ({}, {}) doesn't fit in a {}x{} automata",
+                self.size.width, self.size.height,
+                offset.x, offset.y,
+                automata.size.width, automata.size.height
+            );
+        }
+
+        for &(cell, state) in &self.cells {
+            automata[Point2::new(offset.x + cell.x, offset.y + cell.y)] = state;
+        }
+
+        anyhow::Ok(())
+    }
+
+    // Places the pattern into an otherwise empty automata of the given size
+    pub fn into_automata(self, size: Size, offset: Point2<u32>) -> anyhow::Result<Automata> {
+        let mut automata = Automata::new(size);
+        self.stamp(&mut automata, offset)?;
+
+        anyhow::Ok(automata)
+    }
+}
+
+// Every non-zero cell of an automata, so it can be written out sparsely
+impl From<&Automata> for Pattern {
+    fn from(automata: &Automata) -> Self {
+        let width = automata.size.width;
+        let cells = automata.data
+            .iter()
+            .enumerate()
+            .filter(|(_, &state)| state != 0)
+            .map(|(i, &state)| (Point2::new(i as u32 % width, i as u32 / width), state))
+            .collect();
+
+        Self { size: automata.size, cells, rule: None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_rules_are_skipped() {
+        for name in ["WireWorld", "LifeHistory", "JvN29", "Banks-I", ""] {
+            assert!(rule(name).unwrap().is_none(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn malformed_rulestrings_are_errors() {
+        // A typo, a neighbor count past 8, and Larger-than-Life counts past the neighborhood's 121 cells
+        for rulestring in ["B3/S2x", "B3/S239", "R5,C0,M1,S34..58,B34..122,NM"] {
+            assert!(rule(rulestring).is_err(), "{}", rulestring);
+        }
+    }
+
+    #[test]
+    fn rulestrings_are_parsed() {
+        for rulestring in [" B3/S23 ", "23/3", "B2/S/3", "R5,C0,M1,S34..58,B34..45,NM"] {
+            assert!(rule(rulestring).unwrap().is_some(), "{}", rulestring);
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use std::{
+    borrow,
+    fs
+};
+
+use super::Pattern;
+
+// Reads a single row of cells drawn with `.` for dead cells and any of `alive` for living ones
+// Returns the x coordinates of the living cells
+pub(super) fn row(line: &str, alive: &[char]) -> anyhow::Result<Vec<i32>> {
+    let mut cells = Vec::new();
+    for (x, cell) in line.chars().enumerate() {
+        match cell {
+            '.' => {  },
+            _ if alive.contains(&cell) => cells.push(x as i32),
+            _ => anyhow::bail!("'{}' is neither a living nor a dead cell", cell)
+        }
+    }
+
+    anyhow::Ok(cells)
+}
+
+// Draws every row of the pattern at its full width, so its size survives a round trip
+pub(super) fn rows(pattern: &Pattern, alive: char) -> Vec<String> {
+    let width = pattern.size.width as usize;
+    let mut rows = vec![vec!['.'; width]; pattern.size.height as usize];
+    for (cell, _) in &pattern.cells {
+        rows[cell.y as usize][cell.x as usize] = alive;
+    }
+
+    rows.into_iter().map(|row| row.into_iter().collect()).collect()
+}
+
+fn parse_cells(cells: &str) -> anyhow::Result<Pattern> {
+    let lines = cells
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.starts_with('!'))
+        .collect::<Vec<_>>();
+
+    let mut pattern = Vec::new();
+    for (y, line) in lines.iter().enumerate() {
+        pattern.extend(row(line, &['O', '*'])?.into_iter().map(|x| ((x, y as i32), 1)));
+    }
+
+    // Trailing dead cells still count towards the pattern's size
+    let mut pattern = Pattern::sparse(pattern, None);
+    pattern.size = (
+        lines.iter().map(|line| line.chars().count() as u32).max().unwrap_or(0),
+        lines.len() as u32
+    ).into();
+
+    anyhow::Ok(pattern)
+}
+
+// Reads a pattern from a plaintext `.cells` file
+pub fn pattern_from_cells<C: Into<borrow::Cow<'static, str>>>(file: C) -> anyhow::Result<Pattern> {
+    parse_cells(&fs::read_to_string(&*file.into())?)
+}
+
+impl Pattern {
+    // Writes the pattern out in the plaintext `.cells` format
+    pub fn to_cells(&self) -> anyhow::Result<String> {
+        self.two_state()?;
+
+        let mut cells = String::new();
+        for row in rows(self, 'O') {
+            cells.push_str(&row);
+            cells.push('\n');
+        }
+
+        anyhow::Ok(cells)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_keeps_dead_space() {
+        // A dead first row and column, a blank row in the middle, and dead cells trailing off to the right
+        let cells = "!Name: Test\n......\n.O..O.\n......\n..O...\n";
+        let pattern = parse_cells(cells).unwrap();
+
+        assert_eq!((pattern.size.width, pattern.size.height), (6, 4));
+        assert_eq!(pattern.cells.iter().map(|(cell, _)| (cell.x, cell.y)).collect::<Vec<_>>(), [(1, 1), (4, 1), (2, 3)]);
+        assert_eq!(pattern.to_cells().unwrap(), "......\n.O..O.\n......\n..O...\n");
+    }
+
+    #[test]
+    fn accepts_asterisks() {
+        assert_eq!(parse_cells("*.O\n").unwrap().cells.len(), 2);
+    }
+
+    #[test]
+    fn rejects_unknown_cells() {
+        assert!(parse_cells(".O.\n.x.\n").is_err());
+    }
+
+    #[test]
+    fn refuses_multi_state_patterns() {
+        assert!(Pattern::sparse(vec![((0, 0), 2)], None).to_cells().is_err());
+    }
+}
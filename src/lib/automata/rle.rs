@@ -0,0 +1,288 @@
+use std::{
+    borrow,
+    fmt::Write,
+    fs
+};
+
+use cgmath::Point2;
+
+use crate::rules;
+
+use super::{ pattern, Automata, Pattern, Size };
+
+// Golly's multi-state tags only go as far as `yO`
+const MAX_STATE: u32 = 255;
+
+// Lines are wrapped before they grow past this many characters
+const LINE_LENGTH: usize = 70;
+
+fn header(line: &str) -> anyhow::Result<(Option<Size>, Option<rules::Rule>)> {
+    let (mut width, mut height, mut rule) = (None, None, None);
+    for item in line.split(',') {
+        let (key, value) = item
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("'{}' is not a valid RLE header item", item.trim()))?;
+
+        match key.trim() {
+            "x" => width = Some(value.trim().parse::<u32>()?),
+            "y" => height = Some(value.trim().parse::<u32>()?),
+            // Golly appends bounded grid specifications like `:T100,100`
+            "rule" => {
+                let value = value.trim();
+                let value = value.split(':').next().unwrap_or(value);
+                rule = pattern::rule(value)?;
+            },
+            _ => {  }
+        }
+    }
+
+    let size = width.zip(height).map(Size::from);
+
+    anyhow::Ok((size, rule))
+}
+
+fn parse_rle(rle: &str) -> anyhow::Result<Pattern> {
+    let mut lines = rle
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .peekable();
+
+    let (declared, rule) = match lines.peek() {
+        Some(line) if line.starts_with('x') => header(lines.next().unwrap())?,
+        _ => (None, None)
+    };
+
+    let mut cells = Vec::new();
+    let (mut x, mut y) = (0, 0);
+    let (mut width, mut height) = (0, 0);
+    let mut count: Option<u32> = None;
+    let mut prefix: Option<u32> = None;
+
+    'body: for line in lines {
+        for tag in line.chars() {
+            if prefix.is_some() && !tag.is_ascii_uppercase() {
+                anyhow::bail!("'{}' must be followed by a state between 'A' and 'X'", tag);
+            }
+
+            if let Some(digit) = tag.to_digit(10) {
+                count = match count.unwrap_or(0).checked_mul(10).and_then(|count| count.checked_add(digit)) {
+                    Some(count) => Some(count),
+                    None => anyhow::bail!("a run count is past the largest of {}", u32::MAX)
+                };
+
+                continue;
+            }
+
+            let run = count.take().unwrap_or(1);
+            let past = |position: u32| position
+                .checked_add(run)
+                .ok_or_else(|| anyhow::anyhow!("a run reaches past the largest coordinate of {}", u32::MAX));
+
+            let state = match tag {
+                'b' | '.' => 0,
+                'o' => 1,
+                'A'..='X' => prefix.take().unwrap_or(0) * 24 + (tag as u32 - 'A' as u32 + 1),
+                'p'..='y' => {
+                    prefix = Some(tag as u32 - 'p' as u32 + 1);
+                    count = Some(run);
+                    continue;
+                },
+                '$' => {
+                    x = 0;
+                    y = past(y)?;
+                    continue;
+                },
+                '!' => break 'body,
+                _ if tag.is_whitespace() => continue,
+                _ => anyhow::bail!("'{}' is not a valid RLE tag", tag)
+            };
+
+            let end = past(x)?;
+            if state != 0 {
+                cells.extend((x..end).map(|x| (Point2::new(x, y), state)));
+                width = width.max(end);
+                height = height.max(y.saturating_add(1));
+            }
+
+            x = end;
+        }
+    }
+
+    // The header's dimensions only ever grow the pattern
+    let size = match declared {
+        Some(size) => (size.width.max(width), size.height.max(height)).into(),
+        None => (width, height).into()
+    };
+
+    anyhow::Ok(Pattern { size, cells, rule })
+}
+
+// Reads a pattern from an RLE file, including multi-state files
+pub fn pattern_from_rle<C: Into<borrow::Cow<'static, str>>>(file: C) -> anyhow::Result<Pattern> {
+    parse_rle(&fs::read_to_string(&*file.into())?)
+}
+
+// Places an RLE pattern into an empty automata, with its top-left corner at `offset`
+// The rule named in the file's header is returned alongside it
+pub fn automata_from_rle<C: Into<borrow::Cow<'static, str>>>(
+    file: C,
+    size: Size,
+    offset: Point2<u32>
+) -> anyhow::Result<(Automata, Option<rules::Rule>)> {
+    let mut pattern = pattern_from_rle(file)?;
+    let rule = pattern.rule.take();
+
+    anyhow::Ok((pattern.into_automata(size, offset)?, rule))
+}
+
+// The tag for a single state, in two-state or multi-state notation
+fn tag(state: u32, multistate: bool) -> String {
+    match (state, multistate) {
+        (0, false) => "b".to_string(),
+        (_, false) => "o".to_string(),
+        (0, true) => ".".to_string(),
+        (1..=24, true) => char::from(b'A' + (state - 1) as u8).to_string(),
+        _ => format!(
+            "{}{}",
+            char::from(b'p' + ((state - 1) / 24 - 1) as u8),
+            char::from(b'A' + ((state - 1) % 24) as u8)
+        )
+    }
+}
+
+impl Automata {
+    // Writes every cell out as RLE, keeping the automata's full size
+    // States past 1 switch the file over to multi-state tags
+    pub fn to_rle(&self, rule: Option<&rules::Rule>) -> anyhow::Result<String> {
+        let max = self.data.iter().copied().max().unwrap_or(0);
+        if max > MAX_STATE {
+            anyhow::bail!("state {} is past the {} states RLE can describe", max, MAX_STATE);
+        }
+
+        let multistate = max > 1;
+
+        let mut rle = format!("x = {}, y = {}", self.size.width, self.size.height);
+        if let Some(rule) = rule {
+            write!(rle, ", rule = {}", rule)
+                .map_err(|_| anyhow::anyhow!("the rule can't be written as a rulestring"))?;
+        }
+        rle.push('\n');
+
+        let mut tokens = Vec::new();
+        let mut rows = 0;
+        for row in self.data.chunks_exact(self.size.width as usize) {
+            let mut runs: Vec<(u32, u32)> = Vec::new();
+            for &state in row {
+                match runs.last_mut() {
+                    Some((last, run)) if *last == state => *run += 1,
+                    _ => runs.push((state, 1))
+                }
+            }
+
+            // Dead cells at the end of a row are implied
+            if let Some(&(0, _)) = runs.last() {
+                runs.pop();
+            }
+
+            if !runs.is_empty() {
+                if rows > 0 {
+                    tokens.push(if rows > 1 { format!("{}$", rows) } else { "$".to_string() });
+                }
+
+                rows = 0;
+                tokens.extend(runs.into_iter().map(|(state, run)| if run > 1 {
+                    format!("{}{}", run, tag(state, multistate))
+                } else {
+                    tag(state, multistate)
+                } ));
+            }
+
+            rows += 1;
+        }
+
+        tokens.push("!".to_string());
+
+        let mut line = String::new();
+        for token in tokens {
+            if line.len() + token.len() > LINE_LENGTH {
+                rle.push_str(&line);
+                rle.push('\n');
+                line.clear();
+            }
+
+            line.push_str(&token);
+        }
+
+        rle.push_str(&line);
+        rle.push('\n');
+
+        anyhow::Ok(rle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn two_state_pattern_with_rule() {
+        let glider = parse_rle("#C A glider\nx = 3, y = 3, rule = B3/S23\nbob$2bo$3o!\n").unwrap();
+
+        assert_eq!((glider.size.width, glider.size.height), (3, 3));
+        assert_eq!(glider.rule.map(|rule| rule.to_string()), Some("B3/S23".to_string()));
+
+        let cells = glider.cells.iter().map(|&(cell, state)| (cell.x, cell.y, state)).collect::<Vec<_>>();
+        assert_eq!(cells, [(1, 0, 1), (2, 1, 1), (0, 2, 1), (1, 2, 1), (2, 2, 1)]);
+    }
+
+    #[test]
+    fn named_rules_still_load_their_cells() {
+        let diode = parse_rle("x = 4, y = 1, rule = WireWorld\n.ABC!\n").unwrap();
+
+        assert!(diode.rule.is_none());
+        assert_eq!(diode.into_automata((4, 1).into(), Point2::new(0, 0)).unwrap().data, [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn malformed_rulestrings_fail_the_file() {
+        assert!(parse_rle("x = 3, y = 1, rule = B3/S2x\n3o!\n").is_err());
+    }
+
+    #[test]
+    fn multi_state_round_trip() {
+        // Covers single letters, every prefix boundary and the highest state
+        let mut automata = Automata::new((21, 3).into());
+        for (i, state) in (0..=24).chain([25, 47, 48, 49, 254, 255]).enumerate() {
+            automata.data[i * 2] = state;
+        }
+
+        let rle = automata.to_rle(None).unwrap();
+        let pattern = parse_rle(&rle).unwrap();
+
+        assert_eq!(pattern.into_automata((21, 3).into(), Point2::new(0, 0)).unwrap().data, automata.data);
+    }
+
+    #[test]
+    fn rejects_states_past_the_tags() {
+        let mut automata = Automata::new((2, 1).into());
+        automata.data[1] = 256;
+
+        assert!(automata.to_rle(None).is_err());
+    }
+
+    #[test]
+    fn rejects_overflowing_run_counts() {
+        assert!(parse_rle("x = 1, y = 1\n4294967295b!\n").is_ok());
+        assert!(parse_rle("x = 1, y = 1\n4294967296b!\n").is_err());
+        assert!(parse_rle("x = 1, y = 1\n99999999999o!\n").is_err());
+        assert!(parse_rle("x = 1, y = 1\n2b4294967295b!\n").is_err());
+        assert!(parse_rle("x = 1, y = 1\n2$4294967295$!\n").is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_tags() {
+        assert!(parse_rle("x = 2, y = 1\noz!\n").is_err());
+        assert!(parse_rle("x = 2, y = 1\npo!\n").is_err());
+    }
+}
@@ -0,0 +1,20 @@
+use crate::automata;
+
+// The interface shared by every simulator, whether it runs on the GPU or the CPU
+pub trait Backend {
+    fn automata(&self) -> &automata::Automata;
+
+    // The number of generations that have elapsed so far
+    fn generation(&self) -> u64;
+
+    // Picks the count back up from an earlier run, such as a restored snapshot
+    fn set_generation(&mut self, generation: u64);
+
+    // Advances `generations` generations,
+    // leaving the result accessible through `automata`
+    fn step_n(&mut self, generations: u32);
+
+    fn step(&mut self) {
+        self.step_n(1);
+    }
+}
@@ -0,0 +1,114 @@
+use winit::dpi;
+
+use crate::Gridlines;
+
+// The part of the field the render shader draws, in texture coordinates:
+// the window's bottom-left corner shows `offset`, and the window spans `scale`
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct Uniform {
+    pub(crate) offset: [f32; 2],
+    pub(crate) scale: [f32; 2]
+}
+
+// Gridlines as the render shader sees them, with colors padded out to four components
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct GridUniform {
+    pub(crate) color: [f32; 4],
+    pub(crate) major_color: [f32; 4],
+    pub(crate) thickness: f32,
+    pub(crate) threshold: f32,
+    pub(crate) major: u32,
+    pub(crate) visible: u32
+}
+
+impl GridUniform {
+    pub(crate) fn new(gridlines: &Gridlines, visible: bool) -> Self {
+        let [r, g, b] = gridlines.color;
+        let (major, [mr, mg, mb]) = gridlines.major.unwrap_or((0, [0.0; 3]));
+
+        Self {
+            color: [r, g, b, 1.0],
+            major_color: [mr, mg, mb, 1.0],
+            thickness: gridlines.thickness,
+            threshold: gridlines.threshold,
+            major,
+            visible: visible as u32
+        }
+    }
+}
+
+// Zoom is measured against the field's natural size, where each cell is a single pixel
+// (or a hexagon of unit width), so cells keep their shape whatever the window's size
+#[derive(Clone, Copy)]
+pub(crate) struct Camera {
+    // The texture coordinate in the middle of the window
+    pub(crate) center: (f32, f32),
+    // Window pixels per pixel of the natural size
+    pub(crate) zoom: f32,
+    // Whether the camera keeps fitting the field to the window as it's resized
+    pub(crate) fitted: bool
+}
+
+impl Camera {
+    pub(crate) fn fit(window: dpi::PhysicalSize<u32>, extent: (f32, f32)) -> Self {
+        Self {
+            center: (0.5, 0.5),
+            zoom: (window.width as f32 / extent.0).min(window.height as f32 / extent.1),
+            fitted: true
+        }
+    }
+
+    pub(crate) fn uniform(&self, window: dpi::PhysicalSize<u32>, extent: (f32, f32)) -> Uniform {
+        let scale = [
+            window.width as f32 / (self.zoom * extent.0),
+            window.height as f32 / (self.zoom * extent.1)
+        ];
+
+        Uniform {
+            offset: [self.center.0 - scale[0] * 0.5, self.center.1 - scale[1] * 0.5],
+            scale
+        }
+    }
+
+    // The texture coordinate under a position in the window
+    pub(crate) fn tex(
+        &self,
+        window: dpi::PhysicalSize<u32>,
+        extent: (f32, f32),
+        position: dpi::PhysicalPosition<f64>
+    ) -> (f32, f32) {
+        let Uniform { offset, scale } = self.uniform(window, extent);
+
+        // Window positions start at the top-left, texture coordinates at the bottom-left
+        (
+            offset[0] + position.x as f32 / window.width as f32 * scale[0],
+            offset[1] + (1.0 - position.y as f32 / window.height as f32) * scale[1]
+        )
+    }
+
+    // Zooms by `factor`, keeping whatever is under `position` in place
+    pub(crate) fn zoom(
+        &mut self,
+        window: dpi::PhysicalSize<u32>,
+        extent: (f32, f32),
+        position: dpi::PhysicalPosition<f64>,
+        factor: f32
+    ) {
+        let (x, y) = self.tex(window, extent, position);
+
+        self.center = (x + (self.center.0 - x) / factor, y + (self.center.1 - y) / factor);
+        self.zoom *= factor;
+        self.fitted = false;
+    }
+
+    // Moves the field along with a drag of `delta` window pixels
+    pub(crate) fn pan(&mut self, window: dpi::PhysicalSize<u32>, extent: (f32, f32), delta: (f64, f64)) {
+        let scale = self.uniform(window, extent).scale;
+
+        self.center.0 -= delta.0 as f32 / window.width as f32 * scale[0];
+        self.center.1 += delta.1 as f32 / window.height as f32 * scale[1];
+        self.fitted = false;
+    }
+}
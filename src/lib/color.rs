@@ -1,12 +1,12 @@
 use std::{
-    ops::RangeInclusive, 
+    ops::RangeInclusive,
     borrow::Cow
 };
 
 pub fn color_shader(cr: Vec<Coloring>) -> Cow<'static, str> {
     let mut color_shader = "".to_string();
     color_shader.push_str("fn get_color(state: u32) -> vec3<f32> {");
-    for rule in cr { color_shader.push_str(&rule.cs); }
+    for rule in cr { color_shader.push_str(&rule.shader()); }
 
     color_shader.push_str("return vec3<f32>(0.0, 0.0, 0.0);");
     color_shader.push('}');
@@ -14,44 +14,84 @@ pub fn color_shader(cr: Vec<Coloring>) -> Cow<'static, str> {
     color_shader.into()
 }
 
+// Mirrors the shader's `get_color`, the first coloring to match a state wins
+pub fn get_color(cr: &[Coloring], state: u32) -> [f32; 3] {
+    cr.iter()
+        .find_map(|rule| rule.color(state))
+        .unwrap_or([0.0; 3])
+}
+
+#[derive(Clone)]
+pub(crate) enum Rule {
+    Lerp(RangeInclusive<u32>, [f32; 3], [f32; 3]),
+    Alive([f32; 3]),
+    Map(u32, [f32; 3]),
+    MapRange(RangeInclusive<u32>, [f32; 3])
+}
+
 #[derive(Clone)]
-pub struct Coloring { cs: String }
+pub struct Coloring { pub(crate) rule: Rule }
+
+impl Coloring {
+    fn shader(&self) -> String {
+        match &self.rule {
+            Rule::Lerp(range, start, end) => format!("
+                if state >= {}u && state <= {}u {{
+                    let s = f32(state - {}u) / f32({});
+                    return mix(
+                        vec3<f32>({:?}, {:?}, {:?}),
+                        vec3<f32>({:?}, {:?}, {:?}),
+                        vec3<f32>(s, s, s)
+                    );
+                }}",
+                range.start(), range.end(),
+                range.start(), range.end() - range.start(),
+                start[0], start[1], start[2], end[0], end[1], end[2]
+            ),
+            Rule::Alive(color) => format!("
+                if state != 0u {{ return vec3<f32>({:?}, {:?}, {:?}); }}",
+                color[0], color[1], color[2]
+            ),
+            Rule::Map(state, color) => format!(
+                "if state == {}u {{ return vec3<f32>({:?}, {:?}, {:?}); }}",
+                state, color[0], color[1], color[2]
+            ),
+            Rule::MapRange(range, color) => format!("
+                if(state >= {}u && state <= {}u) {{
+                    return vec3<f32>({:?}, {:?}, {:?});
+                }}",
+                range.start(), range.end(), color[0], color[1], color[2]
+            )
+        }
+    }
+
+    // The color given to `state`, if this coloring covers it
+    pub fn color(&self, state: u32) -> Option<[f32; 3]> {
+        match &self.rule {
+            Rule::Lerp(range, start, end) if range.contains(&state) => {
+                let s = (state - range.start()) as f32 / (range.end() - range.start()) as f32;
+                Some([0, 1, 2].map(|i| start[i] + (end[i] - start[i]) * s))
+            },
+            &Rule::Alive(color) if state != 0 => Some(color),
+            &Rule::Map(mapped, color) if mapped == state => Some(color),
+            Rule::MapRange(range, color) if range.contains(&state) => Some(*color),
+            _ => None
+        }
+    }
+}
 
 pub fn lerp(range: RangeInclusive<u32>, start: [f32; 3], end: [f32; 3]) -> Coloring {
-    Coloring { cs: format!("
-        if state >= {}u && state <= {}u {{
-            let s = f32(state - {}u) / f32({});
-            return mix(
-                vec3<f32>({:?}, {:?}, {:?}), 
-                vec3<f32>({:?}, {:?}, {:?}),
-                vec3<f32>(s, s, s)
-            );
-        }}",
-        range.start(), range.end(), 
-        range.start(), range.end() - range.start(),
-        start[0], start[1], start[2], end[0], end[1], end[2]
-    ) }
+    Coloring { rule: Rule::Lerp(range, start, end) }
 }
 
 pub fn alive(color: [f32; 3]) -> Coloring {
-    Coloring { cs: format!("
-        if state != 0u {{ return vec3<f32>({:?}, {:?}, {:?}); }}",
-        color[0], color[1], color[2]
-    ) }
+    Coloring { rule: Rule::Alive(color) }
 }
 
 pub fn map(state: u32, color: [f32; 3]) -> Coloring {
-    Coloring { cs: format!(
-        "if state == {}u {{ return vec3<f32>({:?}, {:?}, {:?}); }}",
-        state, color[0], color[1], color[2]
-    ) }
+    Coloring { rule: Rule::Map(state, color) }
 }
 
 pub fn map_range(range: RangeInclusive<u32>, color: [f32; 3]) -> Coloring {
-    Coloring { cs: format!("
-        if(state >= {}u && state <= {}u) {{
-            return vec3<f32>({:?}, {:?}, {:?});
-        }}",
-        range.start(), range.end(), color[0], color[1], color[2]
-    ) }
-}
\ No newline at end of file
+    Coloring { rule: Rule::MapRange(range, color) }
+}
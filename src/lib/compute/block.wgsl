@@ -0,0 +1,20 @@
+fn main_cs(@builtin(global_invocation_id) id: vec3<u32>) {
+    if(id.x < size.width && id.y < size.height) {
+        let index = id.x + id.y * size.width;
+        let coord = vec2<i32>(i32(id.x), i32(id.y));
+
+        // The top-left cell of the block this cell belongs to
+        let origin = coord - ((coord - vec2<i32>(i32(phase))) & vec2<i32>(1));
+
+        var block = block_rule(array<u32, 4>(
+            cell(origin),
+            cell(origin + vec2<i32>(1, 0)),
+            cell(origin + vec2<i32>(0, 1)),
+            cell(origin + vec2<i32>(1, 1))
+        ));
+
+        let corner = coord - origin;
+        updated[index] = block[corner.x + corner.y * 2];
+        textureStore(output_texture, coord, vec4<f32>(get_color(current[index]), 1.0));
+    }
+}
\ No newline at end of file
@@ -7,8 +7,9 @@ struct Size {
     height: u32 
 }
 
+// NEIGHBORHOOD_SIZE and WEIGHTS are declared ahead of this header
 struct Neighborhood { 
-    cells: array<u32, 8> 
+    cells: array<u32, NEIGHBORHOOD_SIZE> 
 }
 
 //
@@ -18,6 +19,10 @@ struct Neighborhood {
 @group(0) @binding(0)
 var<uniform> size: Size;
 
+// Which of the two block partitions is active, for Margolus neighborhoods
+@group(0) @binding(1)
+var<uniform> phase: u32;
+
 @group(1) @binding(0)
 var<storage, read> current: array<u32>;
 
@@ -40,32 +45,22 @@ fn wrap(coord: vec2<i32>) -> u32 {
     return u32(n_c.x) + u32(n_c.y) * size.width;
 }
 
-fn moore(coord: vec2<i32>) -> Neighborhood {
-    var neighborhood: Neighborhood;
-    neighborhood.cells = array<u32, 8>();
-
-    neighborhood.cells[0] = current[wrap(vec2<i32>(coord.x, coord.y - 1))];
-    neighborhood.cells[1] = current[wrap(vec2<i32>(coord.x - 1, coord.y))];
-    neighborhood.cells[2] = current[wrap(vec2<i32>(coord.x + 1, coord.y))];
-    neighborhood.cells[3] = current[wrap(vec2<i32>(coord.x, coord.y + 1))];
-    neighborhood.cells[4] = current[wrap(vec2<i32>(coord.x - 1, coord.y - 1))];
-    neighborhood.cells[5] = current[wrap(vec2<i32>(coord.x + 1, coord.y - 1))];
-    neighborhood.cells[6] = current[wrap(vec2<i32>(coord.x - 1, coord.y + 1))];
-    neighborhood.cells[7] = current[wrap(vec2<i32>(coord.x + 1, coord.y + 1))];
+// Steps from a cell by an axial offset on a hexagonal grid
+// Cells are stored in rows, where every odd row is shifted right by half a cell
+fn hex(coord: vec2<i32>, offset: vec2<i32>) -> vec2<i32> {
+    let q = coord.x - (coord.y - (coord.y & 1)) / 2 + offset.x;
+    let r = coord.y + offset.y;
 
-    return neighborhood;
+    return vec2<i32>(q + (r - (r & 1)) / 2, r);
 }
 
-fn von_neumann(coord: vec2<i32>) -> Neighborhood {
-    var neighborhood: Neighborhood;
-    neighborhood.cells = array<u32, 8>();
+fn mirror(value: i32, extent: i32) -> i32 {
+    var m = value % (2 * extent);
 
-    neighborhood.cells[0] = current[wrap(vec2<i32>(coord.x, coord.y - 1))];
-    neighborhood.cells[1] = current[wrap(vec2<i32>(coord.x - 1, coord.y))];
-    neighborhood.cells[2] = current[wrap(vec2<i32>(coord.x + 1, coord.y))];
-    neighborhood.cells[3] = current[wrap(vec2<i32>(coord.x, coord.y + 1))];
+    if(m < 0) { m += 2 * extent; }
+    if(m >= extent) { m = 2 * extent - 1 - m; }
 
-    return neighborhood;
+    return m;
 }
 
 //
@@ -73,47 +68,45 @@ fn von_neumann(coord: vec2<i32>) -> Neighborhood {
 //
 
 fn living(neighborhood: Neighborhood) -> u32 {
+    var cells = neighborhood.cells;
     var neighbor_count = 0u;
     
-    if(neighborhood.cells[0] != 0u) { neighbor_count++; }
-    if(neighborhood.cells[1] != 0u) { neighbor_count++; }
-    if(neighborhood.cells[2] != 0u) { neighbor_count++; }
-    if(neighborhood.cells[3] != 0u) { neighbor_count++; }
-    if(neighborhood.cells[4] != 0u) { neighbor_count++; }
-    if(neighborhood.cells[5] != 0u) { neighbor_count++; }
-    if(neighborhood.cells[6] != 0u) { neighbor_count++; }
-    if(neighborhood.cells[7] != 0u) { neighbor_count++; }
+    for(var i = 0u; i < NEIGHBORHOOD_SIZE; i++) {
+        if(cells[i] != 0u) { neighbor_count++; }
+    }
 
     return neighbor_count;
 }
 
 fn matching(neighborhood: Neighborhood, state: u32) -> u32 {
+    var cells = neighborhood.cells;
     var neighbor_count = 0u;
     
-    if(neighborhood.cells[0] == state) { neighbor_count++; }
-    if(neighborhood.cells[1] == state) { neighbor_count++; }
-    if(neighborhood.cells[2] == state) { neighbor_count++; }
-    if(neighborhood.cells[3] == state) { neighbor_count++; }
-    if(neighborhood.cells[4] == state) { neighbor_count++; }
-    if(neighborhood.cells[5] == state) { neighbor_count++; }
-    if(neighborhood.cells[6] == state) { neighbor_count++; }
-    if(neighborhood.cells[7] == state) { neighbor_count++; }
+    for(var i = 0u; i < NEIGHBORHOOD_SIZE; i++) {
+        if(cells[i] == state) { neighbor_count++; }
+    }
 
     return neighbor_count;
 }
 
-fn up(neighborhood: Neighborhood) -> u32 {
-    return neighborhood.cells[0];
-}
+fn weighted_sum(neighborhood: Neighborhood) -> f32 {
+    var cells = neighborhood.cells;
+    var sum = 0.0;
 
-fn left(neighborhood: Neighborhood) -> u32 {
-    return neighborhood.cells[1];
-}
+    for(var i = 0u; i < NEIGHBORHOOD_SIZE; i++) {
+        if(cells[i] != 0u) { sum += WEIGHTS[i]; }
+    }
 
-fn right(neighborhood: Neighborhood) -> u32 {
-    return neighborhood.cells[2];
+    return sum;
 }
 
-fn down(neighborhood: Neighborhood) -> u32 {
-    return neighborhood.cells[3];
+fn weighted_matching(neighborhood: Neighborhood, state: u32) -> f32 {
+    var cells = neighborhood.cells;
+    var sum = 0.0;
+
+    for(var i = 0u; i < NEIGHBORHOOD_SIZE; i++) {
+        if(cells[i] == state) { sum += WEIGHTS[i]; }
+    }
+
+    return sum;
 }
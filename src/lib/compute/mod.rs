@@ -0,0 +1,719 @@
+use std::{
+    mem,
+    sync::{ Arc, Mutex },
+    cell::Cell
+};
+
+use wgpu::util::DeviceExt;
+
+use crate::{
+    automata,
+    color,
+    Boundary,
+    Config,
+    Neighborhood,
+    Topology
+};
+
+// Picks the largest workgroup dimension (up to 16) that evenly divides the field
+pub(crate) fn workgroup(size: automata::Size) -> u32 {
+    let mut workgroup = 1u32;
+    for i in 2..=16u32 {
+        if size.width % i == 0 && size.height % i == 0 {
+            workgroup = i;
+        }
+    }
+
+    workgroup
+}
+
+// Builds the `cell` method, which reads the cell at the given coordinate,
+// even when it lies outside of the field
+fn boundary_shader(boundary: Boundary) -> String {
+    let body = match boundary {
+        Boundary::Torus => "
+            return current[wrap(coord)];".to_string(),
+        Boundary::Constant(state) => format!("
+            if(coord.x < 0 || coord.x >= i32(size.width) || coord.y < 0 || coord.y >= i32(size.height)) {{
+                return {}u;
+            }}
+
+            return current[wrap(coord)];", state),
+        Boundary::Reflect => "
+            let x = mirror(coord.x, i32(size.width));
+            let y = mirror(coord.y, i32(size.height));
+
+            return current[wrap(vec2<i32>(x, y))];".to_string(),
+        Boundary::Cylinder => "
+            if(coord.y < 0 || coord.y >= i32(size.height)) { return 0u; }
+
+            return current[wrap(coord)];".to_string(),
+        Boundary::KleinBottle => "
+            var n_c = coord;
+
+            // Crossing the top or bottom edge an odd number of times flips the field
+            var turns = coord.y / i32(size.height);
+            if(coord.y % i32(size.height) < 0) { turns -= 1; }
+            if(turns % 2 != 0) { n_c.x = i32(size.width) - 1 - n_c.x; }
+
+            return current[wrap(n_c)];".to_string()
+    };
+
+    format!("fn cell(coord: vec2<i32>) -> u32 {{{}\n}}", body)
+}
+
+// Builds the `neighborhood` method, which gathers the cells at each offset,
+// along with a helper for each adjacent cell the neighborhood contains
+fn neighborhood_shader(offsets: &[(i32, i32)], topology: Topology) -> String {
+    let mut neighborhood_shader = "".to_string();
+    neighborhood_shader.push_str("fn neighborhood(coord: vec2<i32>) -> Neighborhood {");
+    neighborhood_shader.push_str("var neighborhood: Neighborhood;");
+    neighborhood_shader.push_str("neighborhood.cells = array<u32, NEIGHBORHOOD_SIZE>();");
+    for (i, (dx, dy)) in offsets.iter().enumerate() {
+        let coord = match topology {
+            Topology::Square => format!("coord + vec2<i32>({}, {})", dx, dy),
+            Topology::Hexagonal => format!("hex(coord, vec2<i32>({}, {}))", dx, dy)
+        };
+
+        neighborhood_shader.push_str(&format!("
+            neighborhood.cells[{}] = cell({});", i, coord
+        ));
+    }
+
+    neighborhood_shader.push_str("return neighborhood;");
+    neighborhood_shader.push('}');
+
+    for &(name, offset) in topology.helpers() {
+        if let Some(i) = offsets.iter().position(|&o| o == offset) {
+            neighborhood_shader.push_str(&format!("
+                fn {}(neighborhood: Neighborhood) -> u32 {{ return neighborhood.cells[{}]; }}",
+                name, i
+            ));
+        }
+    }
+
+    neighborhood_shader
+}
+
+// Declares the weight of each cell in the neighborhood
+fn weights_shader(weights: &[f32]) -> String {
+    format!(
+        "var<private> WEIGHTS: array<f32, NEIGHBORHOOD_SIZE> = array<f32, NEIGHBORHOOD_SIZE>({});",
+        weights
+            .iter()
+            .map(|weight| format!("{:?}", weight))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+// The shader is built at runtime to support any given coloring scheme
+pub(crate) fn shader(
+    config: &Config<'_>,
+    size: automata::Size
+) -> anyhow::Result<wgpu::ShaderModuleDescriptor<'static>> {
+    let workgroup = workgroup(size);
+
+    // Block rules see their whole block at once, so there's no neighborhood to gather
+    if config.neighborhood == Neighborhood::Margolus {
+        // Otherwise the last column or row would belong to two blocks once the partition shifts
+        if size.width % 2 != 0 || size.height % 2 != 0 {
+            anyhow::bail!("block rules need even dimensions, not {}x{}", size.width, size.height);
+        }
+
+        if config.topology == Topology::Hexagonal {
+            anyhow::bail!("block rules only run on a square grid");
+        }
+
+        // Blocks that straddle an edge would be built from cells past it, which aren't written back,
+        // so reversible rules would lose or duplicate whatever crosses the edge
+        if !matches!(config.boundary, Boundary::Torus) {
+            anyhow::bail!("block rules only run with a torus boundary");
+        }
+
+        return anyhow::Ok(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(
+                [
+                    "let NEIGHBORHOOD_SIZE: u32 = 4u;",
+                    &weights_shader(&[1.0; 4]),
+                    include_str!("header.wgsl"),
+                    &boundary_shader(config.boundary),
+                    &color::color_shader(config.coloring.to_vec()),
+                    &config.state_shader,
+                    &format!("@compute @workgroup_size({}, {}, 1)", workgroup, workgroup),
+                    include_str!("block.wgsl"),
+                    &format!("@compute @workgroup_size({}, {}, 1)", workgroup, workgroup),
+                    include_str!("color.wgsl")
+                ].join("\n").into()
+            )
+        } );
+    }
+
+    let offsets = config.neighborhood.offsets(config.topology);
+    if offsets.is_empty() {
+        anyhow::bail!("the neighborhood must contain at least one cell");
+    }
+
+    // WGSL has no literals for these, so they'd only show up as a shader compilation error
+    let weights = config.neighborhood.weights(config.topology);
+    if let Some((offset, weight)) = offsets.iter().zip(&weights).find(|(_, weight)| !weight.is_finite()) {
+        anyhow::bail!("the weight of ({}, {}) is {}, but weights have to be finite", offset.0, offset.1, weight);
+    }
+
+    anyhow::Ok(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(
+            [
+                &format!("let NEIGHBORHOOD_SIZE: u32 = {}u;", offsets.len()),
+                &weights_shader(&weights),
+                include_str!("header.wgsl"),
+                &boundary_shader(config.boundary),
+                &neighborhood_shader(&offsets, config.topology),
+                &color::color_shader(config.coloring.to_vec()),
+                &config.state_shader,
+                &format!("@compute @workgroup_size({}, {}, 1)", workgroup, workgroup),
+                include_str!("tail.wgsl"),
+                &format!("@compute @workgroup_size({}, {}, 1)", workgroup, workgroup),
+                include_str!("color.wgsl")
+            ].join("\n").into()
+        )
+    } )
+}
+
+// Everything needed to advance the simulation on the GPU,
+// independent of any window or surface
+pub(crate) struct Compute {
+    pub(crate) size: automata::Size,
+    pub(crate) size_group_layout: wgpu::BindGroupLayout,
+    pub(crate) size_group: wgpu::BindGroup,
+    pub(crate) phase_buffer: wgpu::Buffer,
+    pub(crate) cell_buffers: (wgpu::Buffer, wgpu::Buffer),
+    pub(crate) cell_groups: (wgpu::BindGroup, wgpu::BindGroup),
+    pub(crate) texture: wgpu::Texture,
+    pub(crate) texture_view: wgpu::TextureView,
+    pub(crate) compute_texture_group: wgpu::BindGroup,
+    pub(crate) compute_pipeline: wgpu::ComputePipeline,
+    pub(crate) color_pipeline: wgpu::ComputePipeline,
+    pub(crate) workgroup: u32,
+    pub(crate) generation: u64,
+}
+
+impl Compute {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        shader_descriptor: wgpu::ShaderModuleDescriptor<'static>,
+        automata: &automata::Automata
+    ) -> Self {
+        //
+        // DIMENSION BUFFER AND BIND GROUPS
+        //
+
+        let size_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::cast_slice(&[automata.size]),
+                usage: wgpu::BufferUsages::UNIFORM,
+            }
+        );
+
+        // Rewritten before every dispatch to alternate the block partition
+        let phase_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::cast_slice(&[0u32]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+
+        let size_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::all(),
+                        count: None,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        }
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        count: None,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        }
+                    }
+                ]
+            }
+        );
+
+        let size_group = device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &size_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: size_buffer.as_entire_binding()
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: phase_buffer.as_entire_binding()
+                    }
+                ]
+            }
+        );
+
+        //
+        // OUTPUT TEXTURE CREATION
+        //
+
+        let extent = wgpu::Extent3d {
+            width: automata.size.width,
+            height: automata.size.height,
+            depth_or_array_layers: 1,
+        };
+
+        let texture = device.create_texture(
+            &wgpu::TextureDescriptor {
+                label: None,
+                size: extent,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba32Float,
+                usage: wgpu::TextureUsages::STORAGE_BINDING
+                    | wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC,
+            }
+        );
+
+        let texture_view = texture.create_view(
+            &wgpu::TextureViewDescriptor {
+                label: None,
+                format: Some(wgpu::TextureFormat::Rgba32Float),
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                aspect: wgpu::TextureAspect::All,
+                base_mip_level: 0,
+                mip_level_count: std::num::NonZeroU32::new(1),
+                base_array_layer: 0,
+                array_layer_count: std::num::NonZeroU32::new(1),
+
+            }
+        );
+
+        //
+        // CELL BUFFERS AND PIPELINE
+        //
+
+        let cell_buffers = (
+            device.create_buffer_init(
+                &wgpu::util::BufferInitDescriptor {
+                    label: None,
+                    contents: &{
+                        automata.data
+                            .iter()
+                            .map(|x| u32::to_ne_bytes(*x))
+                            .flat_map(|f| f.into_iter())
+                            .collect::<Vec<_>>()
+                    },
+                    usage: wgpu::BufferUsages::STORAGE
+                        | wgpu::BufferUsages::MAP_READ
+                        | wgpu::BufferUsages::COPY_DST
+                }
+            ),
+            device.create_buffer(
+                &wgpu::BufferDescriptor {
+                    label: None,
+                    size: (automata.data.len() * 4) as wgpu::BufferAddress,
+                    usage: wgpu::BufferUsages::STORAGE
+                        | wgpu::BufferUsages::MAP_READ
+                        | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }
+            )
+        );
+
+        let cell_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        count: None,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        }
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        count: None,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                            ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        }
+                    }
+                ],
+            }
+        );
+
+        let cell_groups = (
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &cell_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: cell_buffers.0.as_entire_binding()
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: cell_buffers.1.as_entire_binding()
+                    }
+                ]
+            } ),
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &cell_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: cell_buffers.1.as_entire_binding()
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: cell_buffers.0.as_entire_binding()
+                    }
+                ]
+            } )
+        );
+
+        let compute_texture_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: wgpu::TextureFormat::Rgba32Float,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    }
+                ],
+            }
+        );
+
+        let compute_texture_group = device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &compute_texture_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&texture_view),
+                    }
+                ],
+            }
+        );
+
+        let compute_pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: None,
+                push_constant_ranges: &[],
+                bind_group_layouts: &[
+                    &size_group_layout,
+                    &cell_group_layout,
+                    &compute_texture_group_layout
+                ]
+            }
+        );
+
+        let module = device.create_shader_module(shader_descriptor);
+
+        let compute_pipeline = device.create_compute_pipeline(
+            &wgpu::ComputePipelineDescriptor {
+                label: None,
+                layout: Some(&compute_pipeline_layout),
+                module: &module,
+                entry_point: "main_cs",
+            }
+        );
+
+        // Only colors the current generation, for when its cells are written from outside
+        let color_pipeline = device.create_compute_pipeline(
+            &wgpu::ComputePipelineDescriptor {
+                label: None,
+                layout: Some(&compute_pipeline_layout),
+                module: &module,
+                entry_point: "color_cs",
+            }
+        );
+
+        Self {
+            size: automata.size,
+            size_group_layout,
+            size_group,
+            phase_buffer,
+            cell_buffers,
+            cell_groups,
+            texture,
+            texture_view,
+            compute_texture_group,
+            compute_pipeline,
+            color_pipeline,
+            workgroup: workgroup(automata.size),
+            generation: 0
+        }
+    }
+
+    // Advances the simulation by a single generation
+    // The cell data stays on the GPU until `read` is called
+    pub(crate) fn dispatch(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let phase = (self.generation % 2) as u32;
+        queue.write_buffer(&self.phase_buffer, 0, bytemuck::cast_slice(&[phase]));
+
+        let desc = wgpu::CommandEncoderDescriptor { label: None };
+        let mut encoder = device.create_command_encoder(&desc);
+
+        {
+            let desc = wgpu::ComputePassDescriptor { label: None };
+            let mut compute_pass = encoder.begin_compute_pass(&desc);
+
+            // Access to dimensions, cell arrays and...
+            compute_pass.set_bind_group(0, &self.size_group, &[]);
+            compute_pass.set_bind_group(1, &self.cell_groups.0, &[]);
+
+            // ...output texture for the render attachment
+            compute_pass.set_bind_group(2, &self.compute_texture_group, &[]);
+
+            compute_pass.set_pipeline(&self.compute_pipeline);
+
+            compute_pass.dispatch_workgroups(
+                self.size.width / self.workgroup,
+                self.size.height / self.workgroup,
+                1
+            );
+        }
+
+        queue.submit(Some(encoder.finish()));
+
+        // Swap the `current` and `updated` cell arrays for the next gen
+        mem::swap(&mut self.cell_buffers.0, &mut self.cell_buffers.1);
+        mem::swap(&mut self.cell_groups.0, &mut self.cell_groups.1);
+
+        self.generation += 1;
+    }
+
+    // Dispatches `generations` generations in a row,
+    // reading the cells back once all of them have finished
+    // `main_cs` colors the generation each dispatch starts from, so the last one is colored on its own,
+    // keeping the output texture on the same generation as the cells that are read back
+    pub(crate) fn step_n(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        generations: u32
+    ) -> Option<Vec<u32>> {
+        for _ in 0..generations {
+            self.dispatch(device, queue);
+        }
+
+        if generations > 0 {
+            self.color(device, queue);
+        }
+
+        self.read(device)
+    }
+
+    // Replaces the current generation's cell data, starting at the cell with index `start`,
+    // and redraws the output texture so the change shows up without a step
+    pub(crate) fn write(&self, device: &wgpu::Device, queue: &wgpu::Queue, start: usize, data: &[u32]) {
+        let offset = (start * mem::size_of::<u32>()) as wgpu::BufferAddress;
+        queue.write_buffer(&self.cell_buffers.0, offset, bytemuck::cast_slice(data));
+
+        self.color(device, queue);
+    }
+
+    // Draws the current generation's cells to the output texture
+    fn color(&self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let desc = wgpu::CommandEncoderDescriptor { label: None };
+        let mut encoder = device.create_command_encoder(&desc);
+
+        {
+            let desc = wgpu::ComputePassDescriptor { label: None };
+            let mut compute_pass = encoder.begin_compute_pass(&desc);
+
+            compute_pass.set_bind_group(0, &self.size_group, &[]);
+            compute_pass.set_bind_group(1, &self.cell_groups.0, &[]);
+            compute_pass.set_bind_group(2, &self.compute_texture_group, &[]);
+
+            compute_pass.set_pipeline(&self.color_pipeline);
+
+            compute_pass.dispatch_workgroups(
+                self.size.width / self.workgroup,
+                self.size.height / self.workgroup,
+                1
+            );
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+
+    // Reads back the colors of the output texture, as 8-bit RGBA rows
+    // These belong to the current generation, as drawn in the window
+    pub(crate) fn read_frame(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Vec<u8>> {
+        // Rows in the copy have to start on a 256 byte boundary
+        let row = self.size.width * 16;
+        let padded_row = row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+        let buffer = device.create_buffer(
+            &wgpu::BufferDescriptor {
+                label: None,
+                size: (padded_row * self.size.height) as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }
+        );
+
+        let desc = wgpu::CommandEncoderDescriptor { label: None };
+        let mut encoder = device.create_command_encoder(&desc);
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(padded_row),
+                    rows_per_image: std::num::NonZeroU32::new(self.size.height),
+                }
+            },
+            wgpu::Extent3d {
+                width: self.size.width,
+                height: self.size.height,
+                depth_or_array_layers: 1,
+            }
+        );
+
+        queue.submit(Some(encoder.finish()));
+
+        let buffer_slice = buffer.slice(..);
+
+        let ready = Arc::new(Mutex::new(Cell::new(false)));
+        let ready_ref = Arc::clone(&ready);
+        buffer_slice.map_async(wgpu::MapMode::Read, move |_| {
+            ready_ref.lock().unwrap().set(true);
+        } );
+
+        device.poll(wgpu::Maintain::Wait);
+
+        if ready.lock().unwrap().get() {
+            let data = buffer_slice.get_mapped_range();
+            let frame = data
+                .chunks_exact(padded_row as usize)
+                .flat_map(|padded| padded[..row as usize].chunks_exact(4))
+                .map(|c| f32::from_ne_bytes(c.try_into().unwrap()))
+                .map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+                .collect::<Vec<_>>();
+
+            drop(data);
+            buffer.unmap();
+
+            Some(frame)
+        } else {
+            None
+        }
+    }
+
+    // Reads back the current generation's cell data
+    pub(crate) fn read(&self, device: &wgpu::Device) -> Option<Vec<u32>> {
+        // Get the current buffer's data as a slice
+        let buffer_slice = self.cell_buffers.0.slice(..);
+
+        // Wait for the callback from map_async before proceeding
+        let ready = Arc::new(Mutex::new(Cell::new(false)));
+        let ready_ref = Arc::clone(&ready);
+        buffer_slice.map_async(wgpu::MapMode::Read, move |_| {
+            ready_ref.lock().unwrap().set(true);
+        } );
+
+        // Wait for GPU to finish
+        device.poll(wgpu::Maintain::Wait);
+
+        // Read the cell data from the buffer slice
+        if ready.lock().unwrap().get() {
+            let data = buffer_slice.get_mapped_range();
+            let result = data
+                .chunks_exact(4)
+                .map(|b| u32::from_ne_bytes(b.try_into().unwrap()))
+                .collect::<Vec<_>>();
+
+            drop(data);
+            self.cell_buffers.0.unmap();
+
+            Some(result)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_rules_need_an_even_square_grid() {
+        let config = Config { neighborhood: Neighborhood::Margolus, ..Config::default() };
+        assert!(shader(&config, (8, 8).into()).is_ok());
+        assert!(shader(&config, (8, 7).into()).is_err());
+        assert!(shader(&config, (7, 8).into()).is_err());
+
+        let config = Config { topology: Topology::Hexagonal, ..config };
+        assert!(shader(&config, (8, 8).into()).is_err());
+    }
+
+    #[test]
+    fn weights_have_to_be_finite() {
+        let weighted = |weight: f32| Config {
+            neighborhood: Neighborhood::Weighted(vec![((-1, 0), 1.0), ((1, 0), weight)]),
+            ..Config::default()
+        };
+
+        assert!(shader(&weighted(0.5), (8, 8).into()).is_ok());
+        for weight in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            assert_eq!(
+                shader(&weighted(weight), (8, 8).into()).err().unwrap().to_string(),
+                format!("the weight of (1, 0) is {}, but weights have to be finite", weight)
+            );
+        }
+    }
+
+    #[test]
+    fn block_rules_need_a_torus() {
+        for boundary in [Boundary::Constant(0), Boundary::Reflect, Boundary::Cylinder, Boundary::KleinBottle] {
+            let config = Config { neighborhood: Neighborhood::Margolus, boundary, ..Config::default() };
+            assert!(shader(&config, (8, 8).into()).is_err());
+        }
+    }
+}
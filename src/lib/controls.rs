@@ -0,0 +1,424 @@
+use std::{
+    collections::{ BTreeMap, VecDeque },
+    mem,
+    time
+};
+
+pub use winit::event::VirtualKeyCode;
+
+use cgmath::Point2;
+use winit::{
+    dpi,
+    event
+};
+
+use crate::{
+    automata,
+    backend::Backend,
+    Config,
+    Neighborhood,
+    Simulation,
+    Topology
+};
+
+// Everything the keyboard can do to a running window
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    PlayPause,
+    // Advances a single generation, pausing first
+    Step,
+    // Goes back a generation through the history, pausing first
+    StepBack,
+    // Doubles or halves the generations per second
+    Faster,
+    Slower,
+    // Goes back to the automata the window was opened with
+    Reset,
+    // Picks the state the brush paints with
+    BrushState(u32),
+    // Grows or shrinks the brush by one cell
+    BrushLarger,
+    BrushSmaller,
+    // Cycles through the brush shapes
+    BrushShape,
+    // Zooms in or out around the middle of the window
+    ZoomIn,
+    ZoomOut,
+    // Fits the whole field to the window, undoing any zooming and panning
+    FitToWindow,
+    Gridlines,
+    // Shows or hides the generation, population, tick rate and cursor readout
+    Hud
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Shape {
+    Square,
+    Diamond,
+    Circle
+}
+
+// What clicking and dragging paints into the window
+// On a hexagonal grid, every shape covers the hexagons within `radius` steps
+#[derive(Clone, Copy)]
+pub struct Brush {
+    pub state: u32,
+    pub radius: u32,
+    pub shape: Shape
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self { state: 1, radius: 0, shape: Shape::Square }
+    }
+}
+
+impl Brush {
+    // Offsets from the cell under the cursor, measured like the matching neighborhood
+    pub(crate) fn offsets(&self, topology: Topology) -> Vec<(i32, i32)> {
+        let radius = self.radius;
+        let neighborhood = match self.shape {
+            Shape::Square => Neighborhood::Moore { radius },
+            Shape::Diamond => Neighborhood::VonNeumann { radius },
+            Shape::Circle => Neighborhood::Circular { radius }
+        };
+
+        let mut offsets = neighborhood.offsets(topology);
+        offsets.push((0, 0));
+
+        offsets
+    }
+}
+
+#[derive(Clone)]
+pub struct Controls {
+    // The first binding for a key wins, and an action can have several keys
+    pub bindings: Vec<(VirtualKeyCode, Action)>,
+    // How many bytes of earlier generations are kept for `StepBack`
+    // Each one is a full copy of the cells, so larger grids keep fewer of them
+    pub history_bytes: usize,
+    // The brush the window starts out with
+    pub brush: Brush
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        Self {
+            bindings: vec![
+                (VirtualKeyCode::Space, Action::PlayPause),
+                (VirtualKeyCode::Right, Action::Step),
+                (VirtualKeyCode::Period, Action::Step),
+                (VirtualKeyCode::Left, Action::StepBack),
+                (VirtualKeyCode::Comma, Action::StepBack),
+                (VirtualKeyCode::Up, Action::Faster),
+                (VirtualKeyCode::Down, Action::Slower),
+                (VirtualKeyCode::R, Action::Reset),
+                (VirtualKeyCode::Key0, Action::BrushState(0)),
+                (VirtualKeyCode::Key1, Action::BrushState(1)),
+                (VirtualKeyCode::Key2, Action::BrushState(2)),
+                (VirtualKeyCode::Key3, Action::BrushState(3)),
+                (VirtualKeyCode::Key4, Action::BrushState(4)),
+                (VirtualKeyCode::Key5, Action::BrushState(5)),
+                (VirtualKeyCode::Key6, Action::BrushState(6)),
+                (VirtualKeyCode::Key7, Action::BrushState(7)),
+                (VirtualKeyCode::Key8, Action::BrushState(8)),
+                (VirtualKeyCode::Key9, Action::BrushState(9)),
+                (VirtualKeyCode::RBracket, Action::BrushLarger),
+                (VirtualKeyCode::LBracket, Action::BrushSmaller),
+                (VirtualKeyCode::B, Action::BrushShape),
+                (VirtualKeyCode::Equals, Action::ZoomIn),
+                (VirtualKeyCode::Minus, Action::ZoomOut),
+                (VirtualKeyCode::F, Action::FitToWindow),
+                (VirtualKeyCode::G, Action::Gridlines),
+                (VirtualKeyCode::H, Action::Hud)
+            ],
+            history_bytes: 64 << 20,
+            brush: Brush::default()
+        }
+    }
+}
+
+impl Controls {
+    pub fn action(&self, key: VirtualKeyCode) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == key)
+            .map(|&(_, action)| action)
+    }
+}
+
+// What `run` keeps track of between frames to carry out the controls
+pub(crate) struct Playback {
+    pub(crate) paused: bool,
+    pub(crate) fps: u32,
+    pub(crate) brush: Brush,
+    initial: automata::Automata,
+    history: VecDeque<(u64, automata::Automata)>,
+    limit: usize,
+    // Where the cursor is in the window, and the cell the last stroke painted
+    cursor: Option<dpi::PhysicalPosition<f64>>,
+    stroke: Option<Point2<u32>>,
+    painting: bool,
+    panning: bool,
+    hud: bool,
+    // When each step of the last second happened, to measure the tick rate
+    ticks: VecDeque<time::Instant>,
+    // The HUD is redrawn at most every `HUD_INTERVAL`, and populations are only recounted after a change
+    hud_drawn: Option<time::Instant>,
+    population: Option<Vec<(u32, u64)>>
+}
+
+const HUD_INTERVAL: time::Duration = time::Duration::from_millis(100);
+
+// Populations past this many states are summed up in a single line
+const HUD_STATES: usize = 16;
+
+// Each notch of the scroll wheel, or press of a zoom key, zooms by this much
+const ZOOM: f32 = 1.25;
+
+impl Playback {
+    pub(crate) fn new(initial: automata::Automata, config: &Config<'_>) -> Self {
+        let generation = (initial.data.len() * mem::size_of::<u32>()).max(1);
+        let limit = config.controls.history_bytes / generation;
+
+        Self {
+            paused: false,
+            fps: config.fps.max(1),
+            brush: config.controls.brush,
+            initial,
+            history: VecDeque::with_capacity(limit),
+            limit,
+            cursor: None,
+            stroke: None,
+            painting: false,
+            panning: false,
+            hud: config.hud,
+            ticks: VecDeque::new(),
+            hud_drawn: None,
+            population: None
+        }
+    }
+
+    // Steps the simulation, remembering the generation it leaves behind
+    pub(crate) fn step(&mut self, simulation: &mut Simulation) {
+        if self.limit > 0 {
+            if self.history.len() == self.limit {
+                self.history.pop_front();
+            }
+
+            self.history.push_back((simulation.generation(), simulation.read_cells().clone()));
+        }
+
+        simulation.step();
+
+        self.ticks.push_back(time::Instant::now());
+        self.population = None;
+    }
+
+    pub(crate) fn perform(&mut self, action: Action, simulation: &mut Simulation) {
+        // Anything could have changed, so the HUD is brought up to date right away
+        self.hud_drawn = None;
+
+        match action {
+            Action::PlayPause => self.paused = !self.paused,
+            Action::Step => {
+                self.paused = true;
+                self.step(simulation);
+            },
+            Action::StepBack => {
+                self.paused = true;
+                if let Some((generation, automata)) = self.history.pop_back() {
+                    simulation.write_cells(automata).unwrap();
+                    simulation.set_generation(generation);
+                    self.population = None;
+                }
+            },
+            Action::Faster => self.fps = self.fps.saturating_mul(2),
+            Action::Slower => self.fps = (self.fps / 2).max(1),
+            Action::Reset => {
+                simulation.write_cells(self.initial.clone()).unwrap();
+                simulation.set_generation(0);
+                self.history.clear();
+                self.population = None;
+            },
+            Action::BrushState(state) => self.brush.state = state,
+            Action::BrushLarger => self.brush.radius = self.brush.radius.saturating_add(1),
+            Action::BrushSmaller => self.brush.radius = self.brush.radius.saturating_sub(1),
+            Action::BrushShape => self.brush.shape = match self.brush.shape {
+                Shape::Square => Shape::Diamond,
+                Shape::Diamond => Shape::Circle,
+                Shape::Circle => Shape::Square
+            },
+            Action::ZoomIn => simulation.zoom(Self::middle(simulation), ZOOM),
+            Action::ZoomOut => simulation.zoom(Self::middle(simulation), ZOOM.recip()),
+            Action::FitToWindow => simulation.fit_to_window(),
+            Action::Gridlines => simulation.toggle_gridlines(),
+            Action::Hud => {
+                self.hud = !self.hud;
+                if !self.hud {
+                    simulation.set_overlay(None);
+                }
+            }
+        }
+    }
+
+    // Redraws the HUD, if it's showing and hasn't been redrawn too recently
+    pub(crate) fn update_hud(&mut self, simulation: &mut Simulation) {
+        let now = time::Instant::now();
+        while self.ticks.front().is_some_and(|&tick| now - tick > time::Duration::from_secs(1)) {
+            self.ticks.pop_front();
+        }
+
+        if !self.hud || self.hud_drawn.is_some_and(|drawn| now - drawn < HUD_INTERVAL) {
+            return;
+        }
+
+        self.hud_drawn = Some(now);
+
+        let automata = simulation.read_cells();
+        let population = self.population.get_or_insert_with(|| {
+            // Small states are counted straight into a list, which is much faster than a map
+            let mut small = vec![0u64; 256];
+            let mut large = BTreeMap::new();
+            for &state in &automata.data {
+                match small.get_mut(state as usize) {
+                    Some(count) => *count += 1,
+                    None => *large.entry(state).or_insert(0u64) += 1
+                }
+            }
+
+            small
+                .into_iter()
+                .enumerate()
+                .map(|(state, count)| (state as u32, count))
+                .filter(|&(_, count)| count > 0)
+                .chain(large)
+                .collect()
+        } );
+
+        let mut text = format!("generation {}\n", simulation.generation());
+        text += &format!(
+            "ticks/s {} / {}{}\n",
+            self.ticks.len(), self.fps, if self.paused { " (paused)" } else { "" }
+        );
+
+        text += &match self.cursor.and_then(|cursor| simulation.cell_at(cursor)) {
+            Some(automata::Cell::Square(cell)) => {
+                format!("cell {}, {}: state {}\n", cell.x, cell.y, automata[cell])
+            },
+            Some(automata::Cell::Hex(hex)) => {
+                format!("hex {}, {}: state {}\n", hex.q, hex.r, automata[hex])
+            },
+            None => "cell -\n".to_string()
+        };
+
+        text += &format!(
+            "brush {}: {:?} {}\n",
+            self.brush.state, self.brush.shape, self.brush.radius
+        );
+
+        for &(state, count) in population.iter().take(HUD_STATES) {
+            text += &format!("state {}: {}\n", state, count);
+        }
+
+        if population.len() > HUD_STATES {
+            text += &format!("+{} more states\n", population.len() - HUD_STATES);
+        }
+
+        simulation.set_overlay(Some(&text));
+    }
+
+    fn middle(simulation: &Simulation) -> dpi::PhysicalPosition<f64> {
+        let size = simulation.state.physical_size;
+        dpi::PhysicalPosition::new(size.width as f64 / 2.0, size.height as f64 / 2.0)
+    }
+
+    pub(crate) fn cursor_moved(&mut self, position: dpi::PhysicalPosition<f64>, simulation: &mut Simulation) {
+        if let (true, Some(last)) = (self.panning, self.cursor) {
+            simulation.pan((position.x - last.x, position.y - last.y));
+        }
+
+        self.cursor = Some(position);
+
+        if self.painting {
+            self.paint(simulation);
+        }
+    }
+
+    pub(crate) fn cursor_left(&mut self) {
+        self.cursor = None;
+        self.stroke = None;
+    }
+
+    // The left button paints, the right and middle buttons drag the field around
+    pub(crate) fn mouse(&mut self, button: event::MouseButton, pressed: bool, simulation: &mut Simulation) {
+        match button {
+            event::MouseButton::Left => {
+                self.painting = pressed;
+                self.stroke = None;
+
+                if pressed {
+                    self.paint(simulation);
+                }
+            },
+            event::MouseButton::Right | event::MouseButton::Middle => self.panning = pressed,
+            _ => {  }
+        }
+    }
+
+    // Zooms around the cursor
+    pub(crate) fn scroll(&mut self, delta: event::MouseScrollDelta, simulation: &mut Simulation) {
+        let notches = match delta {
+            event::MouseScrollDelta::LineDelta(_, y) => y,
+            event::MouseScrollDelta::PixelDelta(position) => position.y as f32 / 50.0
+        };
+
+        let position = self.cursor.unwrap_or_else(|| Self::middle(simulation));
+        simulation.zoom(position, ZOOM.powf(notches));
+    }
+
+    // Paints the brush at the cell under the cursor,
+    // along with every cell in between it and the last one of the stroke, so fast drags leave no gaps
+    fn paint(&mut self, simulation: &mut Simulation) {
+        let size = simulation.read_cells().size();
+        let cell = match self.cursor.and_then(|cursor| simulation.cell_at(cursor)) {
+            Some(cell) => cell.position(size).unwrap(),
+            None => {
+                self.stroke = None;
+                return;
+            }
+        };
+
+        if self.stroke == Some(cell) {
+            return;
+        }
+
+        let from = self.stroke.unwrap_or(cell);
+        let (run, rise) = (cell.x as i64 - from.x as i64, cell.y as i64 - from.y as i64);
+        let steps = run.abs().max(rise.abs()).max(1);
+
+        let topology = simulation.state.topology;
+        let offsets = self.brush.offsets(topology);
+
+        let mut cells = Vec::new();
+        for step in 1..=steps {
+            let x = (from.x as i64 + run * step / steps) as i32;
+            let y = (from.y as i64 + rise * step / steps) as i32;
+
+            cells.extend(offsets.iter().filter_map(|&(dx, dy)| match topology {
+                Topology::Square => {
+                    let (x, y) = (x + dx, y + dy);
+                    (x >= 0 && y >= 0).then(|| automata::Cell::Square(Point2::new(x as u32, y as u32)))
+                },
+                Topology::Hexagonal => {
+                    let hex = automata::Hex::from(Point2::new(x as u32, y as u32));
+                    Some(automata::Cell::Hex(automata::Hex::new(hex.q + dx, hex.r + dy)))
+                }
+            } ));
+        }
+
+        simulation.paint(cells, self.brush.state);
+        self.stroke = Some(cell);
+        self.population = None;
+    }
+}
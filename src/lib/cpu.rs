@@ -0,0 +1,460 @@
+use std::mem;
+
+use crate::{
+    automata,
+    backend::Backend,
+    Boundary,
+    Config,
+    Topology
+};
+
+// The cells surrounding the one being updated,
+// in the same order as the `Neighborhood` struct in the compute shader
+pub struct Neighborhood<'a> {
+    cells: &'a [u32],
+    offsets: &'a [(i32, i32)],
+    weights: &'a [f32],
+    topology: Topology
+}
+
+impl Neighborhood<'_> {
+    pub fn cells(&self) -> &[u32] {
+        self.cells
+    }
+
+    // Returns the number of non-zero cells in the neighborhood
+    pub fn living(&self) -> u32 {
+        self.cells.iter().filter(|&&cell| cell != 0).count() as u32
+    }
+
+    // Returns the number of cells with the given state in the neighborhood
+    pub fn matching(&self, state: u32) -> u32 {
+        self.cells.iter().filter(|&&cell| cell == state).count() as u32
+    }
+
+    // Returns the total weight of the non-zero cells in the neighborhood
+    pub fn weighted_sum(&self) -> f32 {
+        self.cells
+            .iter()
+            .zip(self.weights.iter())
+            .filter(|(&cell, _)| cell != 0)
+            .map(|(_, weight)| weight)
+            .sum()
+    }
+
+    // Returns the total weight of the cells with the given state in the neighborhood
+    pub fn weighted_matching(&self, state: u32) -> f32 {
+        self.cells
+            .iter()
+            .zip(self.weights.iter())
+            .filter(|(&cell, _)| cell == state)
+            .map(|(_, weight)| weight)
+            .sum()
+    }
+
+    // Like the compute shader's helpers, these are only usable when the topology has a helper by that name
+    // and the neighborhood contains the corresponding cell
+    fn adjacent(&self, helper: &str) -> u32 {
+        let offset = self.topology
+            .helpers()
+            .iter()
+            .find(|&&(name, _)| name == helper)
+            .map(|&(_, offset)| offset)
+            .unwrap_or_else(|| panic!("`{}` isn't one of this topology's helpers", helper));
+
+        let index = self.offsets
+            .iter()
+            .position(|&o| o == offset)
+            .expect("the neighborhood doesn't contain this cell");
+
+        self.cells[index]
+    }
+
+    // Adjacent cells on a square grid
+    pub fn up(&self) -> u32 {
+        self.adjacent("up")
+    }
+
+    pub fn down(&self) -> u32 {
+        self.adjacent("down")
+    }
+
+    // Adjacent cells on either kind of grid
+    pub fn left(&self) -> u32 {
+        self.adjacent("left")
+    }
+
+    pub fn right(&self) -> u32 {
+        self.adjacent("right")
+    }
+
+    // Adjacent cells on a hexagonal grid, in axial offsets
+    pub fn up_left(&self) -> u32 {
+        self.adjacent("up_left")
+    }
+
+    pub fn up_right(&self) -> u32 {
+        self.adjacent("up_right")
+    }
+
+    pub fn down_left(&self) -> u32 {
+        self.adjacent("down_left")
+    }
+
+    pub fn down_right(&self) -> u32 {
+        self.adjacent("down_right")
+    }
+}
+
+// The Rust-side counterpart to a state shader's `main` function
+pub trait Rule {
+    fn next(&self, neighborhood: &Neighborhood<'_>, state: u32) -> u32;
+}
+
+impl<F> Rule for F where F: Fn(&Neighborhood<'_>, u32) -> u32 {
+    fn next(&self, neighborhood: &Neighborhood<'_>, state: u32) -> u32 {
+        self(neighborhood, state)
+    }
+}
+
+// Mirrors the compute shader's `wrap` helper
+fn wrap(size: automata::Size, x: i32, y: i32) -> usize {
+    let x = x.rem_euclid(size.width as i32) as u32;
+    let y = y.rem_euclid(size.height as i32) as u32;
+
+    (x + y * size.width) as usize
+}
+
+// Mirrors the compute shader's `hex` helper
+pub(crate) fn hex(x: i32, y: i32, dq: i32, dr: i32) -> (i32, i32) {
+    let q = x - (y - (y & 1)) / 2 + dq;
+    let r = y + dr;
+
+    (q + (r - (r & 1)) / 2, r)
+}
+
+// Mirrors the compute shader's `mirror` helper
+fn mirror(value: i32, extent: i32) -> i32 {
+    let m = value.rem_euclid(2 * extent);
+    if m >= extent { 2 * extent - 1 - m } else { m }
+}
+
+// Mirrors the generated `cell` method for each kind of boundary
+fn cell(data: &[u32], size: automata::Size, boundary: Boundary, x: i32, y: i32) -> u32 {
+    let (width, height) = (size.width as i32, size.height as i32);
+    match boundary {
+        Boundary::Torus => data[wrap(size, x, y)],
+        Boundary::Constant(state) => {
+            if x < 0 || x >= width || y < 0 || y >= height {
+                state
+            } else {
+                data[wrap(size, x, y)]
+            }
+        },
+        Boundary::Reflect => data[wrap(size, mirror(x, width), mirror(y, height))],
+        Boundary::Cylinder => {
+            if y < 0 || y >= height { 0 } else { data[wrap(size, x, y)] }
+        },
+        Boundary::KleinBottle => {
+            // Crossing the top or bottom edge an odd number of times flips the field
+            let x = if y.div_euclid(height) % 2 != 0 { width - 1 - x } else { x };
+            data[wrap(size, x, y)]
+        }
+    }
+}
+
+// Runs an automata on the CPU, which needs no adapter at all
+// The state shader in the `Config` is ignored in favor of `rule`
+pub struct Cpu<R: Rule> {
+    pub(crate) automata: automata::Automata,
+    pub(crate) updated: Vec<u32>,
+    pub(crate) offsets: Vec<(i32, i32)>,
+    pub(crate) weights: Vec<f32>,
+    pub(crate) boundary: Boundary,
+    pub(crate) topology: Topology,
+    pub(crate) rule: R,
+    pub(crate) generation: u64
+}
+
+impl<R: Rule> Cpu<R> {
+    pub fn new(
+        automata: automata::Automata,
+        config: &Config<'_>,
+        rule: R
+    ) -> anyhow::Result<Self> {
+        if config.neighborhood == crate::Neighborhood::Margolus {
+            anyhow::bail!("block rules only run on the GPU");
+        }
+
+        anyhow::Ok(Self {
+            updated: vec![0; automata.data.len()],
+            automata,
+            offsets: config.neighborhood.offsets(config.topology),
+            weights: config.neighborhood.weights(config.topology),
+            boundary: config.boundary,
+            topology: config.topology,
+            rule,
+            generation: 0
+        } )
+    }
+
+    pub fn run(mut self, generations: u32) -> automata::Automata {
+        self.step_n(generations);
+        self.automata
+    }
+}
+
+impl<R: Rule> Backend for Cpu<R> {
+    fn automata(&self) -> &automata::Automata {
+        &self.automata
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    fn step_n(&mut self, generations: u32) {
+        let size = self.automata.size;
+        let mut cells = vec![0; self.offsets.len()];
+
+        for _ in 0..generations {
+            for y in 0..size.height as i32 {
+                for x in 0..size.width as i32 {
+                    for (neighbor, &(dx, dy)) in cells.iter_mut().zip(self.offsets.iter()) {
+                        let (nx, ny) = match self.topology {
+                            Topology::Square => (x + dx, y + dy),
+                            Topology::Hexagonal => hex(x, y, dx, dy)
+                        };
+
+                        *neighbor = cell(&self.automata.data, size, self.boundary, nx, ny);
+                    }
+
+                    let index = wrap(size, x, y);
+                    self.updated[index] = self.rule.next(
+                        &Neighborhood {
+                            cells: &cells,
+                            offsets: &self.offsets,
+                            weights: &self.weights,
+                            topology: self.topology
+                        },
+                        self.automata.data[index]
+                    );
+                }
+            }
+
+            mem::swap(&mut self.automata.data, &mut self.updated);
+
+            self.generation += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Point2;
+
+    use super::*;
+    use crate::{ automata::Hex, rules, Neighborhood as Shape };
+
+    fn automata(size: (u32, u32), cells: &[(u32, u32)]) -> automata::Automata {
+        let mut automata = automata::Automata::new(size.into());
+        for &(x, y) in cells {
+            automata[Point2::new(x, y)] = 1;
+        }
+
+        automata
+    }
+
+    // Every non-zero cell, row by row
+    fn living(automata: &automata::Automata) -> Vec<(u32, u32)> {
+        let width = automata.size.width;
+        let mut cells = automata.data
+            .iter()
+            .enumerate()
+            .filter(|(_, &state)| state != 0)
+            .map(|(i, _)| (i as u32 % width, i as u32 / width))
+            .collect::<Vec<_>>();
+
+        cells.sort_by_key(|&(x, y)| (y, x));
+        cells
+    }
+
+    fn life(automata: automata::Automata, boundary: Boundary, generations: u32) -> automata::Automata {
+        let config = Config { boundary, ..Config::default() };
+        let rule = "B3/S23".parse::<rules::Rule>().unwrap();
+
+        Cpu::new(automata, &config, rule).unwrap().run(generations)
+    }
+
+    // Moves down and to the right by a cell every four generations
+    const GLIDER: [(u32, u32); 5] = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+
+    fn glider(dx: u32, dy: u32) -> Vec<(u32, u32)> {
+        GLIDER.iter().map(|&(x, y)| (x + dx, y + dy)).collect()
+    }
+
+    #[test]
+    fn blinker_oscillates() {
+        let horizontal = automata((5, 5), &[(1, 2), (2, 2), (3, 2)]);
+
+        assert_eq!(living(&life(horizontal.clone(), Boundary::Torus, 1)), [(2, 1), (2, 2), (2, 3)]);
+        assert_eq!(living(&life(horizontal.clone(), Boundary::Torus, 2)), living(&horizontal));
+    }
+
+    #[test]
+    fn glider_moves_under_every_boundary() {
+        let boundaries = [
+            Boundary::Torus,
+            Boundary::Constant(0),
+            Boundary::Reflect,
+            Boundary::Cylinder,
+            Boundary::KleinBottle
+        ];
+
+        // Away from the edges, every boundary behaves the same
+        for boundary in boundaries {
+            let field = life(automata((10, 10), &glider(2, 2)), boundary, 8);
+            let mut expected = glider(4, 4);
+            expected.sort_by_key(|&(x, y)| (y, x));
+
+            assert_eq!(living(&field), expected);
+        }
+    }
+
+    #[test]
+    fn glider_wraps_around_a_torus() {
+        let start = automata((8, 8), &glider(3, 3));
+
+        assert_eq!(living(&life(start.clone(), Boundary::Torus, 32)), living(&start));
+    }
+
+    #[test]
+    fn glider_wraps_horizontally_around_a_cylinder() {
+        let field = life(automata((8, 40), &glider(5, 1)), Boundary::Cylinder, 32);
+
+        assert_eq!(living(&field), living(&automata((8, 40), &glider(5, 9))));
+    }
+
+    #[test]
+    fn glider_comes_back_mirrored_across_a_klein_bottle() {
+        let (width, height) = (12, 8);
+        let field = life(automata((width, height), &glider(2, 3)), Boundary::KleinBottle, 32);
+
+        // Crossing the bottom edge once mirrors the glider, which had moved 8 cells each way
+        let expected = glider(2, 3)
+            .iter()
+            .map(|&(x, y)| ((width as i32 - 1 - (x as i32 + 8)).rem_euclid(width as i32) as u32, y))
+            .collect::<Vec<_>>();
+
+        assert_eq!(living(&field), living(&automata((width, height), &expected)));
+    }
+
+    #[test]
+    fn constant_boundary_counts_as_neighbors() {
+        // Each edge cell touches three living cells past the edge, each corner five
+        let field = life(automata((3, 3), &[]), Boundary::Constant(1), 1);
+
+        assert_eq!(living(&field), [(1, 0), (0, 1), (2, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn reflect_boundary_mirrors_the_corner() {
+        // The cell's three mirror images make it a block, rather than a lone cell
+        let corner = automata((6, 6), &[(0, 0)]);
+
+        assert_eq!(living(&life(corner.clone(), Boundary::Reflect, 4)), [(0, 0)]);
+        assert!(living(&life(corner, Boundary::Torus, 1)).is_empty());
+    }
+
+    #[test]
+    fn custom_neighborhood_looks_up_helpers_by_offset() {
+        let config = Config {
+            neighborhood: Shape::Custom(vec![(1, 0), (0, -1)]),
+            ..Config::default()
+        };
+
+        // Every cell takes on the state above it, so the field moves down
+        let rule = |neighborhood: &Neighborhood<'_>, _: u32| neighborhood.up();
+        let field = Cpu::new(automata((4, 4), &[(1, 1), (2, 3)]), &config, rule).unwrap().run(1);
+
+        assert_eq!(living(&field), [(2, 0), (1, 2)]);
+    }
+
+    #[test]
+    fn weighted_neighborhood_sums_weights() {
+        let config = Config {
+            neighborhood: Shape::Weighted(vec![((-1, 0), 0.5), ((1, 0), 2.0)]),
+            ..Config::default()
+        };
+
+        let rule = |neighborhood: &Neighborhood<'_>, _: u32| (neighborhood.weighted_sum() * 2.0) as u32;
+        let field = Cpu::new(automata((5, 1), &[(2, 0)]), &config, rule).unwrap().run(1);
+
+        // The cell to the left sees it with a weight of 2, the cell to the right with 0.5
+        assert_eq!(field.data, [0, 4, 0, 1, 0]);
+    }
+
+    #[test]
+    fn generations_cells_decay() {
+        let config = Config::default();
+        let rule = "B3/S/4".parse::<rules::Rule>().unwrap();
+        let mut cpu = Cpu::new(automata((5, 5), &[(2, 2)]), &config, rule).unwrap();
+
+        // A lone cell can't survive, so it steps through each dying state and then dies
+        for state in [2, 3, 0] {
+            cpu.step();
+            assert_eq!(cpu.automata()[Point2::new(2, 2)], state);
+            assert_eq!(living(cpu.automata()).len(), usize::from(state != 0));
+        }
+    }
+
+    #[test]
+    fn generations_dying_cells_are_not_neighbors() {
+        // Brian's Brain: the domino starts dying, and gives birth on both of its sides
+        let config = Config::default();
+        let rule = "B2/S/3".parse::<rules::Rule>().unwrap();
+        let mut cpu = Cpu::new(automata((6, 6), &[(1, 1), (2, 1)]), &config, rule).unwrap();
+
+        cpu.step();
+        let mut expected = automata((6, 6), &[(1, 0), (2, 0), (1, 2), (2, 2)]);
+        expected[Point2::new(1, 1)] = 2;
+        expected[Point2::new(2, 1)] = 2;
+        assert_eq!(cpu.automata().data, expected.data);
+    }
+
+    #[test]
+    fn hexagonal_cells_have_six_neighbors() {
+        let config = Config { topology: Topology::Hexagonal, ..Config::default() };
+        let rule = "B1/S".parse::<rules::Rule>().unwrap();
+
+        let mut start = automata::Automata::new((6, 6).into());
+        start[Hex::new(1, 3)] = 1;
+
+        let field = Cpu::new(start, &config, rule).unwrap().run(1);
+        let neighbors = [(2, 3), (2, 2), (1, 2), (0, 3), (0, 4), (1, 4)].map(|(q, r)| Hex::new(q, r));
+
+        assert!(neighbors.iter().all(|&hex| field[hex] == 1));
+        assert_eq!(living(&field).len(), 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "`up` isn't one of this topology's helpers")]
+    fn hexagonal_grids_have_no_up() {
+        let config = Config { topology: Topology::Hexagonal, ..Config::default() };
+        let rule = |neighborhood: &Neighborhood<'_>, _: u32| neighborhood.up();
+
+        Cpu::new(automata((4, 4), &[]), &config, rule).unwrap().run(1);
+    }
+
+    #[test]
+    fn block_rules_are_refused() {
+        let config = Config { neighborhood: Shape::Margolus, ..Config::default() };
+        let rule = |_: &Neighborhood<'_>, state: u32| state;
+
+        assert!(Cpu::new(automata((4, 4), &[]), &config, rule).is_err());
+    }
+}
@@ -0,0 +1,95 @@
+use crate::{
+    automata,
+    backend::Backend,
+    compute,
+    Config
+};
+
+// Runs an automata on the GPU without a window or surface
+// Useful on CI machines or batch servers with no display attached
+pub struct Headless {
+    pub(crate) automata: automata::Automata,
+
+    pub(crate) device: wgpu::Device,
+    pub(crate) queue: wgpu::Queue,
+    pub(crate) compute: compute::Compute,
+}
+
+async fn adapter(force_fallback_adapter: bool) -> anyhow::Result<wgpu::Adapter> {
+    let instance = wgpu::Instance::new(wgpu::Backends::all());
+
+    instance.request_adapter(
+        &wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface: None,
+            force_fallback_adapter,
+        }
+    ).await.ok_or_else(|| anyhow::anyhow!("no suitable adapter was found"))
+}
+
+// The limits of the adapter `Headless::new` would pick, to size an automata before creating it
+pub async fn limits(force_fallback_adapter: bool) -> anyhow::Result<wgpu::Limits> {
+    anyhow::Ok(adapter(force_fallback_adapter).await?.limits())
+}
+
+impl Headless {
+    // When `force_fallback_adapter` is set, a software adapter
+    // (lavapipe, llvmpipe, WARP) is requested instead of a GPU
+    pub async fn new(
+        automata: automata::Automata,
+        config: &Config<'_>,
+        force_fallback_adapter: bool
+    ) -> anyhow::Result<Self> {
+        let adapter = adapter(force_fallback_adapter).await?;
+
+        // Software adapters often fall short of the default limits,
+        // so request whatever the adapter actually supports
+        let (device, queue) = adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                features: wgpu::Features::empty(),
+                limits: adapter.limits(),
+            },
+            None
+        ).await?;
+
+        let compute = compute::Compute::new(
+            &device,
+            compute::shader(config, automata.size)?,
+            &automata
+        );
+
+        anyhow::Ok(Self { automata, device, queue, compute })
+    }
+
+    // The colors of the current generation, as drawn in the window, as 8-bit RGBA rows
+    pub fn read_frame(&self) -> Option<Vec<u8>> {
+        self.compute.read_frame(&self.device, &self.queue)
+    }
+
+    pub fn run(mut self, generations: u32) -> automata::Automata {
+        self.step_n(generations);
+        self.automata
+    }
+}
+
+impl Backend for Headless {
+    fn automata(&self) -> &automata::Automata {
+        &self.automata
+    }
+
+    fn generation(&self) -> u64 {
+        self.compute.generation
+    }
+
+    fn set_generation(&mut self, generation: u64) {
+        self.compute.generation = generation;
+    }
+
+    // Only reads the cells back once all of the generations have finished
+    fn step_n(&mut self, generations: u32) {
+        if let Some(data) = self.compute.step_n(&self.device, &self.queue, generations) {
+            self.automata.data = data;
+        }
+    }
+}
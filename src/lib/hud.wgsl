@@ -0,0 +1,28 @@
+// Draws the overlay's text, already rasterized into a texture, near the window's top-left corner
+
+@group(0) @binding(0)
+var text: texture_2d<f32>;
+
+// Gap between the text and the window's edges, in pixels
+let MARGIN: i32 = 8;
+
+// A single triangle that covers the whole window
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let x = f32(i32(index & 1u) * 4 - 1);
+    let y = f32(i32(index & 2u) * 2 - 1);
+
+    return vec4<f32>(x, y, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let size = textureDimensions(text);
+    let pixel = vec2<i32>(floor(position.xy)) - vec2<i32>(MARGIN, MARGIN);
+
+    if(pixel.x < 0 || pixel.y < 0 || pixel.x >= size.x || pixel.y >= size.y) {
+        discard;
+    }
+
+    return textureLoad(text, pixel, 0);
+}
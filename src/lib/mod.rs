@@ -1,4 +1,8 @@
 mod state;
+mod compute;
+
+mod simulation;
+pub use simulation::Simulation;
 
 mod vertex;
 pub(crate) use vertex::Vertex;
@@ -6,6 +10,17 @@ pub(crate) use vertex::CLIP_SPACE_EXTREMA;
 
 pub mod automata;
 pub mod color;
+pub mod rules;
+pub mod backend;
+pub mod headless;
+pub mod cpu;
+pub mod stream;
+pub mod snapshot;
+pub mod controls;
+mod camera;
+mod hud;
+#[cfg(feature = "record")]
+pub mod record;
 
 use std::{
     time,
@@ -20,9 +35,243 @@ use winit::{
     event_loop
 };
 
+// Each radius-based neighborhood covers every cell within `radius` of the center,
+// only differing in how that distance is measured
+#[derive(Clone, PartialEq)]
 pub enum Neighborhood {
-    Moore,
-    VonNeumann
+    // Chebyshev distance, a square
+    Moore { radius: u32 },
+    // Manhattan distance, a diamond
+    VonNeumann { radius: u32 },
+    // Euclidean distance, a circle
+    Circular { radius: u32 },
+    // Arbitrary offsets from the center, gathered in the order given
+    Custom(Vec<(i32, i32)>),
+    // Like `Custom`, but each offset also carries a weight
+    Weighted(Vec<((i32, i32), f32)>),
+    // 2x2 blocks that are updated as a whole by a `block_rule`,
+    // with the partition shifting by one cell every other generation
+    Margolus
+}
+
+impl Default for Neighborhood {
+    fn default() -> Self {
+        Neighborhood::Moore { radius: 1 }
+    }
+}
+
+// Every offset within `radius` that the neighborhood contains, in the order they are gathered:
+// the topology's adjacent cells first, then every remaining cell from the top-left to the bottom-right
+pub(crate) fn gather_order(
+    radius: i32,
+    topology: Topology,
+    contains: impl Fn((i32, i32)) -> bool
+) -> Vec<(i32, i32)> {
+    let mut offsets = Vec::new();
+
+    let remaining = (-radius..=radius)
+        .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)));
+
+    for offset in topology.adjacent().iter().copied().chain(remaining) {
+        if offset != (0, 0) && contains(offset) && !offsets.contains(&offset) {
+            offsets.push(offset);
+        }
+    }
+
+    offsets
+}
+
+impl Neighborhood {
+    // On a hexagonal grid, offsets are axial and
+    // every radius-based neighborhood measures the number of steps between hexagons
+    pub(crate) fn offsets(&self, topology: Topology) -> Vec<(i32, i32)> {
+        let hexagonal = |radius: u32| gather_order(radius as i32, topology, |(dq, dr)| {
+            dq.unsigned_abs() + dr.unsigned_abs() + (dq + dr).unsigned_abs() <= 2 * radius
+        } );
+
+        match (self, topology) {
+            (
+                Neighborhood::Moore { radius } |
+                Neighborhood::VonNeumann { radius } |
+                Neighborhood::Circular { radius },
+                Topology::Hexagonal
+            ) => hexagonal(*radius),
+            (&Neighborhood::Moore { radius }, _) => gather_order(radius as i32, topology, |(dx, dy)| {
+                dx.unsigned_abs().max(dy.unsigned_abs()) <= radius
+            } ),
+            (&Neighborhood::VonNeumann { radius }, _) => gather_order(radius as i32, topology, |(dx, dy)| {
+                dx.unsigned_abs() + dy.unsigned_abs() <= radius
+            } ),
+            (&Neighborhood::Circular { radius }, _) => gather_order(radius as i32, topology, |(dx, dy)| {
+                dx.unsigned_abs().pow(2) + dy.unsigned_abs().pow(2) <= radius.pow(2)
+            } ),
+            (Neighborhood::Custom(offsets), _) => offsets.clone(),
+            (Neighborhood::Weighted(weights), _) => {
+                weights.iter().map(|&(offset, _)| offset).collect()
+            },
+            // Blocks are gathered separately, since they depend on the phase
+            (Neighborhood::Margolus, _) => Vec::new()
+        }
+    }
+
+    // Every cell weighs the same unless the neighborhood is `Weighted`
+    pub(crate) fn weights(&self, topology: Topology) -> Vec<f32> {
+        match *self {
+            Neighborhood::Weighted(ref weights) => {
+                weights.iter().map(|&(_, weight)| weight).collect()
+            },
+            _ => vec![1.0; self.offsets(topology).len()]
+        }
+    }
+}
+
+// The shape of each cell and how the cells are laid out
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum Topology {
+    #[default]
+    Square,
+    // Pointy-topped hexagons, where every odd row is shifted right by half a cell
+    // Offsets and `automata::Hex` coordinates are axial
+    Hexagonal
+}
+
+impl Topology {
+    // The cells gathered before any others
+    pub(crate) fn adjacent(&self) -> &'static [(i32, i32)] {
+        match self {
+            Topology::Square => &[
+                (0, -1), (-1, 0), (1, 0), (0, 1),
+                (-1, -1), (1, -1), (-1, 1), (1, 1)
+            ],
+            Topology::Hexagonal => &[
+                (1, 0), (1, -1), (0, -1),
+                (-1, 0), (-1, 1), (0, 1)
+            ]
+        }
+    }
+
+    // Adjacent cells that can be read through a named helper
+    pub(crate) fn helpers(&self) -> &'static [(&'static str, (i32, i32))] {
+        match self {
+            Topology::Square => &[
+                ("up", (0, -1)),
+                ("left", (-1, 0)),
+                ("right", (1, 0)),
+                ("down", (0, 1))
+            ],
+            Topology::Hexagonal => &[
+                ("right", (1, 0)),
+                ("up_right", (1, -1)),
+                ("up_left", (0, -1)),
+                ("left", (-1, 0)),
+                ("down_left", (-1, 1)),
+                ("down_right", (0, 1))
+            ]
+        }
+    }
+
+    // The field's natural size in pixels, where every cell is a pixel wide
+    // Hexagons are taller than they are wide, and odd rows stick out by half a cell
+    pub(crate) fn extent(&self, size: automata::Size) -> (f32, f32) {
+        match self {
+            Topology::Square => (size.width as f32, size.height as f32),
+            Topology::Hexagonal => (
+                size.width as f32 + 0.5,
+                (1.5 * size.height as f32 + 0.5) / 3f32.sqrt()
+            )
+        }
+    }
+
+    pub(crate) fn window_size(&self, size: automata::Size) -> dpi::Size {
+        let (width, height) = self.extent(size);
+
+        dpi::Size::Physical(dpi::PhysicalSize {
+            width: width.round() as u32,
+            height: height.round() as u32
+        } )
+    }
+
+    // The cell drawn at a texture coordinate, mirroring `fs_main` and `fs_hex`
+    pub(crate) fn cell_at(&self, size: automata::Size, tex: (f32, f32)) -> Option<automata::Cell> {
+        let cell = match self {
+            Topology::Square => {
+                let (x, y) = (
+                    (tex.0 * size.width as f32).floor(),
+                    (tex.1 * size.height as f32).floor()
+                );
+
+                if x < 0.0 || y < 0.0 {
+                    return None;
+                }
+
+                automata::Cell::Square(cgmath::Point2::new(x as u32, y as u32))
+            },
+            Topology::Hexagonal => {
+                let sqrt3 = 3f32.sqrt();
+
+                let px = tex.0 * sqrt3 * (size.width as f32 + 0.5) - sqrt3 * 0.5;
+                let py = tex.1 * (1.5 * size.height as f32 + 0.5) - 1.0;
+
+                let q = sqrt3 / 3.0 * px - py / 3.0;
+                let r = 2.0 / 3.0 * py;
+                let s = -q - r;
+
+                let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+                let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+
+                if dq > dr && dq > ds {
+                    rq = -rr - rs;
+                } else if dr > ds {
+                    rr = -rq - rs;
+                }
+
+                automata::Cell::Hex(automata::Hex::new(rq as i32, rr as i32))
+            }
+        };
+
+        cell.position(size).map(|_| cell)
+    }
+}
+
+// Determines what lies past the edges of the field
+#[derive(Clone, Copy, Default)]
+pub enum Boundary {
+    // Wraps around on both axes
+    #[default]
+    Torus,
+    // Every cell past the edge holds the given state
+    Constant(u32),
+    // Mirrors the field back onto itself at every edge
+    Reflect,
+    // Wraps horizontally, cells above and below the field are dead
+    Cylinder,
+    // Wraps horizontally, wrapping vertically also mirrors horizontally
+    KleinBottle
+}
+
+// Lines drawn between cells once they're zoomed in far enough,
+// or around each cell on a hexagonal grid
+#[derive(Clone, Copy)]
+pub struct Gridlines {
+    pub color: [f32; 3],
+    // In pixels
+    pub thickness: f32,
+    // Lines only show up once cells are at least this many pixels wide
+    pub threshold: f32,
+    // Every Nth line is drawn in another color instead, counting from the bottom-left corner
+    // Only square grids have major lines
+    pub major: Option<(u32, [f32; 3])>
+}
+
+impl Default for Gridlines {
+    fn default() -> Self {
+        Self {
+            color: [0.25, 0.25, 0.25],
+            thickness: 1.0,
+            threshold: 8.0,
+            major: None
+        }
+    }
 }
 
 pub struct Config<'a> {
@@ -30,7 +279,34 @@ pub struct Config<'a> {
     pub fps: u32,
     pub state_shader: Cow<'static, str>,
     pub coloring: &'a [color::Coloring],
-    pub neighborhood: Neighborhood
+    pub neighborhood: Neighborhood,
+    pub boundary: Boundary,
+    pub topology: Topology,
+    pub controls: controls::Controls,
+    // The window's inner size, by default the field's natural size with a pixel per cell
+    pub window_size: Option<automata::Size>,
+    // Gridlines start out hidden when `None`, and toggling them on uses the defaults
+    pub gridlines: Option<Gridlines>,
+    // Whether the window opens with the HUD showing
+    pub hud: bool
+}
+
+impl Default for Config<'_> {
+    fn default() -> Self {
+        Self {
+            title: None,
+            fps: 60,
+            state_shader: Cow::Borrowed(""),
+            coloring: &[],
+            neighborhood: Neighborhood::default(),
+            boundary: Boundary::default(),
+            topology: Topology::default(),
+            controls: controls::Controls::default(),
+            window_size: None,
+            gridlines: None,
+            hud: false
+        }
+    }
 }
 
 pub async fn run(
@@ -41,49 +317,22 @@ pub async fn run(
 
     let window = WindowBuilder::new()
         .with_title(config.title.clone().unwrap_or_default())
-        .with_inner_size::<dpi::Size>(automata.size.into())
+        .with_inner_size(match config.window_size {
+            Some(size) => size.into(),
+            None => config.topology.window_size(automata.size)
+        } )
         .build(&event_loop)
         .unwrap();
 
-    let mut workgroup = 1u32;
-    for i in 2..=16u32 {
-        if automata.size.width % i == 0 && automata.size.height % i == 0 {
-            workgroup = i;
-        }
-    }
+    // Kept around so the controls can go back to the first generation
+    let mut playback = controls::Playback::new(automata.clone(), &config);
 
-    let neighborhood = format!("
-        fn neighborhood(coord: vec2<i32>) -> Neighborhood {{ return {}(coord); }}", 
-        match config.neighborhood {
-            Neighborhood::Moore => "moore",
-            Neighborhood::VonNeumann => "von_neumann"
-        } 
-    );
-
-    // The shader is built at runtime to support any given coloring scheme
-    let shader_descriptor = wgpu::ShaderModuleDescriptor {
-        label: None,
-        source: wgpu::ShaderSource::Wgsl(
-            vec![
-                include_str!("./compute/header.wgsl"),
-                &color::color_shader(config.coloring.to_vec()),
-                &config.state_shader,
-                &neighborhood,
-                &format!("@compute @workgroup_size({}, {}, 1)", workgroup, workgroup),
-                include_str!("./compute/tail.wgsl")
-            ].join("\n").into()
-        )
-    };
-
-    // The State struct holds all of the programs mutable state
-    let mut state = state::State::new(
-        &window,
-        shader_descriptor,
-        automata
-    ).await;
+    // The Simulation holds all of the programs mutable state
+    let mut simulation = Simulation::new(&window, automata, &config)
+        .await
+        .unwrap();
 
     // A few variables to keep frame-time consistent when performance allows
-    let fps = (config.fps as f32).recip();
     let mut accumulated_time = 0.0;
     let mut current = time::Instant::now();
 
@@ -102,19 +351,38 @@ pub async fn run(
                     WindowEvent::CloseRequested => {
                         *control_flow = event_loop::ControlFlow::Exit; },
                     WindowEvent::Resized(physical_size) => {
-                        state.resize(*physical_size); },
+                        simulation.resize(*physical_size); },
                     WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                        state.resize(**new_inner_size); },
+                        simulation.resize(**new_inner_size); },
+                    WindowEvent::KeyboardInput {
+                        input: event::KeyboardInput {
+                            state: event::ElementState::Pressed,
+                            virtual_keycode: Some(key),
+                            ..
+                        },
+                        ..
+                    } => if let Some(action) = config.controls.action(*key) {
+                        playback.perform(action, &mut simulation);
+                    },
+                    // The mouse paints with the brush and moves the camera
+                    WindowEvent::CursorMoved { position, .. } => {
+                        playback.cursor_moved(*position, &mut simulation); },
+                    WindowEvent::CursorLeft { .. } => {
+                        playback.cursor_left(); },
+                    WindowEvent::MouseInput { state, button, .. } => {
+                        playback.mouse(*button, *state == event::ElementState::Pressed, &mut simulation); },
+                    WindowEvent::MouseWheel { delta, .. } => {
+                        playback.scroll(*delta, &mut simulation); },
                     _ => {}
                 }
             },
 
             // Called after resizes and after simulation updates
             event::Event::RedrawRequested(window_id) if window_id == window.id() => {
-                match state.render() {
+                match simulation.render() {
                     Ok(_) => {  },
                     Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
-                        state.resize(state.physical_size); }, 
+                        simulation.resize(window.inner_size()); }, 
                     Err(wgpu::SurfaceError::OutOfMemory) => {
                         *control_flow = event_loop::ControlFlow::Exit; }, 
                     Err(wgpu::SurfaceError::Timeout) => {  },
@@ -123,11 +391,17 @@ pub async fn run(
 
             // Simulation updates occur when
             // the accumulated time exceeds the time-per-frame
+            // Time doesn't build up while paused
             event::Event::MainEventsCleared => { 
-                if accumulated_time >= fps {
-                    state.tick();
+                let fps = (playback.fps as f32).recip();
+                if playback.paused {
+                    accumulated_time = 0.0;
+                } else if accumulated_time >= fps {
+                    playback.step(&mut simulation);
                     accumulated_time -= fps;
                 }
+
+                playback.update_hud(&mut simulation);
                 
                 window.request_redraw();
             },
@@ -138,5 +412,5 @@ pub async fn run(
     } );
     
     #[allow(unreachable_code)]
-    state.automata
+    simulation.into_automata()
 }
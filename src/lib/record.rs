@@ -0,0 +1,226 @@
+use std::{
+    collections,
+    fs,
+    io,
+    ops::RangeInclusive,
+    path,
+    time
+};
+
+use crate::{
+    automata,
+    backend::Backend,
+    color
+};
+
+pub struct Options {
+    // Only every Nth generation is captured
+    pub every: u32,
+    // Each cell is drawn as a `scale` by `scale` square
+    pub scale: u32,
+    // How long each frame is shown for
+    pub delay: time::Duration,
+    // The generations to capture, inclusive
+    pub generations: RangeInclusive<u64>
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            every: 1,
+            scale: 1,
+            delay: time::Duration::from_millis(100),
+            generations: 0..=u64::MAX
+        }
+    }
+}
+
+// Backends step at most `u32::MAX` generations at a time
+fn advance<B: Backend>(backend: &mut B, generations: u64) {
+    let mut remaining = generations;
+    while remaining > 0 {
+        let step = remaining.min(u32::MAX as u64) as u32;
+        backend.step_n(step);
+        remaining -= step as u64;
+    }
+}
+
+// Captures generations of any backend as RGBA frames, colored the same way as on screen,
+// and writes them out as an animated GIF or PNG
+pub struct Recorder {
+    coloring: Vec<color::Coloring>,
+    options: Options,
+    size: automata::Size,
+    frames: Vec<Vec<u8>>
+}
+
+impl Recorder {
+    pub fn new(coloring: &[color::Coloring], options: Options) -> Self {
+        Self {
+            coloring: coloring.to_vec(),
+            options: Options { every: options.every.max(1), scale: options.scale.max(1), ..options },
+            size: (0, 0).into(),
+            frames: Vec::new()
+        }
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    // Captures the backend's current generation, if the options ask for it
+    pub fn capture<B: Backend>(&mut self, backend: &B) {
+        let generation = backend.generation();
+        let offset = generation.wrapping_sub(*self.options.generations.start());
+        if !self.options.generations.contains(&generation) || offset % self.options.every as u64 != 0 {
+            return;
+        }
+
+        let automata = backend.automata();
+        let scale = self.options.scale;
+        self.size = (automata.size.width * scale, automata.size.height * scale).into();
+
+        // Every state's color only needs to be worked out once per frame
+        let mut colors = collections::HashMap::new();
+        let mut frame = Vec::with_capacity((self.size.width * self.size.height * 4) as usize);
+        for row in automata.data.chunks_exact(automata.size.width as usize) {
+            let row = row
+                .iter()
+                .flat_map(|&state| {
+                    let color = *colors.entry(state).or_insert_with(|| {
+                        let [r, g, b] = color::get_color(&self.coloring, state)
+                            .map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+
+                        [r, g, b, 255]
+                    } );
+
+                    (0..scale).flat_map(move |_| color)
+                } )
+                .collect::<Vec<_>>();
+
+            for _ in 0..scale {
+                frame.extend_from_slice(&row);
+            }
+        }
+
+        self.frames.push(frame);
+    }
+
+    // Steps the backend through the whole generation range, capturing along the way
+    // The range must have an end, the default one runs forever and is refused
+    pub fn run<B: Backend>(&mut self, backend: &mut B) -> anyhow::Result<()> {
+        let (start, end) = (*self.options.generations.start(), *self.options.generations.end());
+        if end == u64::MAX {
+            anyhow::bail!("the generation range has no end, call `capture` after each step instead");
+        }
+
+        if backend.generation() < start {
+            advance(backend, start - backend.generation());
+        }
+
+        while backend.generation() <= end {
+            self.capture(backend);
+
+            // Generations that won't be captured don't need to be read back
+            let step = self.options.every as u64 - (backend.generation() - start) % self.options.every as u64;
+            advance(backend, step.min(end - backend.generation() + 1));
+        }
+
+        anyhow::Ok(())
+    }
+
+    pub fn save_gif<P: AsRef<path::Path>>(&self, file: P) -> anyhow::Result<()> {
+        if self.frames.is_empty() {
+            anyhow::bail!("no frames have been captured");
+        }
+
+        let (width, height) = match (u16::try_from(self.size.width), u16::try_from(self.size.height)) {
+            (Ok(width), Ok(height)) => (width, height),
+            _ => anyhow::bail!("GIF frames can't be larger than 65535x65535")
+        };
+
+        let mut encoder = gif::Encoder::new(io::BufWriter::new(fs::File::create(file)?), width, height, &[])?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+
+        // GIF delays are counted in hundredths of a second
+        let delay = (self.options.delay.as_millis() / 10).min(u16::MAX as u128) as u16;
+        for frame in &self.frames {
+            let mut frame = gif::Frame::from_rgba_speed(width, height, &mut frame.clone(), 10);
+            frame.delay = delay;
+            encoder.write_frame(&frame)?;
+        }
+
+        anyhow::Ok(())
+    }
+
+    pub fn save_apng<P: AsRef<path::Path>>(&self, file: P) -> anyhow::Result<()> {
+        if self.frames.is_empty() {
+            anyhow::bail!("no frames have been captured");
+        }
+
+        let writer = io::BufWriter::new(fs::File::create(file)?);
+        let mut encoder = png::Encoder::new(writer, self.size.width, self.size.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(self.frames.len() as u32, 0)?;
+
+        let delay = self.options.delay.as_millis().min(u16::MAX as u128) as u16;
+        encoder.set_frame_delay(delay, 1000)?;
+
+        let mut writer = encoder.write_header()?;
+        for frame in &self.frames {
+            writer.write_image_data(frame)?;
+        }
+
+        anyhow::Ok(writer.finish()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Counts generations without simulating anything
+    struct Counter {
+        automata: automata::Automata,
+        generation: u64
+    }
+
+    impl Backend for Counter {
+        fn automata(&self) -> &automata::Automata {
+            &self.automata
+        }
+
+        fn generation(&self) -> u64 {
+            self.generation
+        }
+
+        fn set_generation(&mut self, generation: u64) {
+            self.generation = generation;
+        }
+
+        fn step_n(&mut self, generations: u32) {
+            self.generation += generations as u64;
+        }
+    }
+
+    #[test]
+    fn run_reaches_ranges_past_u32() {
+        let start = 3 * u32::MAX as u64 + 10;
+        let mut counter = Counter { automata: automata::Automata::new((2, 2).into()), generation: 0 };
+        let mut recorder = Recorder::new(&[], Options { every: 3, generations: start..=start + 9, ..Options::default() });
+
+        recorder.run(&mut counter).unwrap();
+
+        // Captures `start`, `start + 3`, `start + 6` and `start + 9`
+        assert_eq!(recorder.frames(), 4);
+        assert_eq!(counter.generation(), start + 10);
+    }
+
+    #[test]
+    fn run_refuses_an_endless_range() {
+        let mut counter = Counter { automata: automata::Automata::new((2, 2).into()), generation: 0 };
+
+        assert!(Recorder::new(&[], Options::default()).run(&mut counter).is_err());
+    }
+}
@@ -20,18 +20,125 @@ var<uniform> size: Size;
 @group(1) @binding(0)
 var output_texture: texture_2d<f32>;
 
+// The window's bottom-left corner shows `offset`, and the window spans `scale`
+struct Camera {
+    offset: vec2<f32>,
+    scale: vec2<f32>
+}
+
+@group(2) @binding(0)
+var<uniform> camera: Camera;
+
+// Colors are RGB, with an unused fourth component for alignment
+struct Gridlines {
+    color: vec4<f32>,
+    major_color: vec4<f32>,
+    thickness: f32,
+    threshold: f32,
+    major: u32,
+    visible: u32
+}
+
+@group(2) @binding(1)
+var<uniform> gridlines: Gridlines;
+
 @vertex
 fn vs_main(in: VertexInput) -> VertexOutput {
     var out: VertexOutput;
     out.clip_pos = vec4<f32>(in.pos, 0.0, 1.0);
-    out.tex = (in.pos + 1.0) * 0.5;
+    out.tex = camera.offset + (in.pos + 1.0) * 0.5 * camera.scale;
     return out;
 }
 
+// 0 away from any line, 1 on a line and 2 on a major line,
+// given the distance in pixels to the nearest line and the index of that line
+fn gridline(distance: f32, cell_pixels: f32, index: i32) -> i32 {
+    if(gridlines.visible == 0u || cell_pixels < gridlines.threshold || distance >= gridlines.thickness * 0.5) {
+        return 0;
+    }
+
+    if(gridlines.major > 0u && index % i32(gridlines.major) == 0) {
+        return 2;
+    }
+
+    return 1;
+}
+
+fn overlay(color: vec4<f32>, level: i32) -> vec4<f32> {
+    if(level == 2) { return vec4<f32>(gridlines.major_color.rgb, 1.0); }
+    if(level == 1) { return vec4<f32>(gridlines.color.rgb, 1.0); }
+
+    return color;
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let x: i32 = i32(in.tex.x * f32(size.width));
-    let y: i32 = i32(in.tex.y * f32(size.height));
+    let cell = in.tex * vec2<f32>(f32(size.width), f32(size.height));
+
+    // Cells per pixel, worked out ahead of any branches
+    let step = fwidth(cell);
+
+    let x: i32 = i32(floor(cell.x));
+    let y: i32 = i32(floor(cell.y));
+
+    if(x < 0 || x >= i32(size.width) || y < 0 || y >= i32(size.height)) {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
 
-    return textureLoad(output_texture, vec2<i32>(x, y), 0);
-}
\ No newline at end of file
+    let edge = round(cell);
+    let distance = abs(cell - edge) / step;
+    let level = max(
+        gridline(distance.x, 1.0 / step.x, i32(edge.x)),
+        gridline(distance.y, 1.0 / step.y, i32(edge.y))
+    );
+
+    return overlay(textureLoad(output_texture, vec2<i32>(x, y), 0), level);
+}
+
+// Draws each cell as a pointy-topped hexagon, with every odd row shifted right by half a cell
+@fragment
+fn fs_hex(in: VertexOutput) -> @location(0) vec4<f32> {
+    let sqrt3 = 1.7320508;
+
+    // Position relative to the center of the first cell, in units of a hexagon's radius
+    let px = in.tex.x * sqrt3 * (f32(size.width) + 0.5) - sqrt3 * 0.5;
+    let py = in.tex.y * (1.5 * f32(size.height) + 0.5) - 1.0;
+
+    // Hexagon radii per pixel, worked out ahead of any branches
+    let step = fwidth(px);
+
+    // Fractional axial coordinates, rounded to the nearest hexagon
+    let q = sqrt3 / 3.0 * px - py / 3.0;
+    let r = 2.0 / 3.0 * py;
+    let s = -q - r;
+
+    var rq = round(q);
+    var rr = round(r);
+    let rs = round(s);
+
+    let dq = abs(rq - q);
+    let dr = abs(rr - r);
+    let ds = abs(rs - s);
+
+    if(dq > dr && dq > ds) {
+        rq = -rr - rs;
+    } else if(dr > ds) {
+        rr = -rq - rs;
+    }
+
+    let row = i32(rr);
+    let column = i32(rq) + (row - (row & 1)) / 2;
+
+    if(row < 0 || row >= i32(size.height) || column < 0 || column >= i32(size.width)) {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+
+    // Each hexagon draws its half of the outline, from the distance to its nearest edge
+    // Major lines don't apply to hexagons
+    let v = vec2<f32>(px - sqrt3 * (f32(column) + 0.5 * f32(row & 1)), py - 1.5 * f32(row));
+    let reach = max(abs(v.x), max(abs(v.x * 0.5 + v.y * sqrt3 * 0.5), abs(v.x * 0.5 - v.y * sqrt3 * 0.5)));
+    let distance = (sqrt3 * 0.5 - reach) / step;
+    let level = min(gridline(distance, sqrt3 / step, 1), 1);
+
+    return overlay(textureLoad(output_texture, vec2<i32>(column, row), 0), level);
+}
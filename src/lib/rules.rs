@@ -0,0 +1,406 @@
+use std::{
+    borrow::Cow,
+    fmt,
+    ops::RangeInclusive,
+    str::FromStr
+};
+
+use crate::{
+    color,
+    cpu,
+    Neighborhood,
+    Topology
+};
+
+// An outer-totalistic rule, optionally with Generations-style dying states
+// Parsed from rulestrings like `B3/S23`, `23/3`, `B36/S23` or `B2/S/3`,
+// as well as Larger-than-Life rules like `R5,C0,M1,S34..58,B34..45,NM`
+#[derive(Clone)]
+pub struct Rule {
+    pub(crate) birth: Vec<RangeInclusive<u32>>,
+    pub(crate) survival: Vec<RangeInclusive<u32>>,
+    pub(crate) states: u32,
+    pub(crate) neighborhood: Neighborhood,
+    // Whether the cell itself counts towards its own neighbors
+    pub(crate) middle: bool
+}
+
+// Collapses neighbor counts into as few ranges as possible
+fn ranges(mut counts: Vec<u32>) -> Vec<RangeInclusive<u32>> {
+    counts.sort_unstable();
+    counts.dedup();
+
+    let mut ranges: Vec<RangeInclusive<u32>> = Vec::new();
+    for count in counts {
+        match ranges.last_mut() {
+            Some(range) if *range.end() + 1 == count => {
+                *range = *range.start()..=count;
+            },
+            _ => ranges.push(count..=count)
+        }
+    }
+
+    ranges
+}
+
+fn counts(digits: &str) -> anyhow::Result<Vec<RangeInclusive<u32>>> {
+    let counts = digits
+        .chars()
+        .map(|digit| match digit.to_digit(10) {
+            Some(count) if count <= 8 => anyhow::Ok(count),
+            _ => anyhow::bail!("'{}' is not a neighbor count", digit)
+        } )
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    anyhow::Ok(ranges(counts))
+}
+
+fn states(states: &str) -> anyhow::Result<u32> {
+    let states = states.trim_start_matches(['C', 'G']);
+    match states.parse::<u32>() {
+        Ok(states) if states >= 2 => anyhow::Ok(states),
+        _ => anyhow::bail!("'{}' is not a valid number of states", states)
+    }
+}
+
+// Parses either a single count (`3`) or an inclusive range of them (`34..58`)
+fn range(range: &str) -> anyhow::Result<RangeInclusive<u32>> {
+    let count = |count: &str| count
+        .parse::<u32>()
+        .map_err(|_| anyhow::anyhow!("'{}' is not a neighbor count", count));
+
+    match range.split_once("..") {
+        Some((start, end)) => anyhow::Ok(count(start)?..=count(end)?),
+        None => anyhow::Ok(count(range)?..=count(range)?)
+    }
+}
+
+fn larger_than_life(rule: &str) -> anyhow::Result<Rule> {
+    let mut radius = 1;
+    let mut states = 2;
+    let mut middle = false;
+    let mut birth = Vec::new();
+    let mut survival = Vec::new();
+    let mut kind = 'M';
+
+    // Bare ranges extend whichever condition came before them
+    let mut survival_last = true;
+    for item in rule.split(',') {
+        let (key, value) = item.split_at(item.chars().next().map_or(0, char::len_utf8));
+        match key {
+            "R" => radius = value.parse::<u32>()?,
+            "C" => states = value.parse::<u32>()?.max(2),
+            "M" => middle = value == "1",
+            "S" => {
+                survival_last = true;
+                if !value.is_empty() { survival.push(range(value)?); }
+            },
+            "B" => {
+                survival_last = false;
+                if !value.is_empty() { birth.push(range(value)?); }
+            },
+            "N" => kind = value.chars().next().unwrap_or('M'),
+            _ if survival_last => survival.push(range(item)?),
+            _ => birth.push(range(item)?)
+        }
+    }
+
+    let neighborhood = match kind {
+        'M' => Neighborhood::Moore { radius },
+        'N' => Neighborhood::VonNeumann { radius },
+        'C' => Neighborhood::Circular { radius },
+        _ => anyhow::bail!("'N{}' is not a known neighborhood", kind)
+    };
+
+    anyhow::Ok(Rule { birth, survival, states, neighborhood, middle })
+}
+
+impl FromStr for Rule {
+    type Err = anyhow::Error;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let rule = rule
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_uppercase();
+
+        if rule.starts_with('R') && rule.contains(',') {
+            let rule = larger_than_life(&rule)?;
+
+            // Neither condition can ask for more neighbors than there are
+            let limit = rule.neighborhood.offsets(Topology::Square).len() as u32 + u32::from(rule.middle);
+            let mut counts = rule.birth.iter().chain(rule.survival.iter());
+            if let Some(range) = counts.find(|range| *range.end() > limit) {
+                anyhow::bail!("'{}' exceeds the {} cells in the neighborhood", range.end(), limit);
+            }
+
+            return anyhow::Ok(rule);
+        }
+
+        let parts = rule.split('/').collect::<Vec<_>>();
+        if parts.len() < 2 || parts.len() > 3 {
+            anyhow::bail!("'{}' is not a valid rulestring", rule);
+        }
+
+        let mut birth = None;
+        let mut survival = None;
+        if parts[..2].iter().any(|part| part.starts_with(['B', 'S'])) {
+            // B/S notation, in either order
+            for part in &parts[..2] {
+                if let Some(digits) = part.strip_prefix('B') {
+                    birth = Some(counts(digits)?);
+                } else if let Some(digits) = part.strip_prefix('S') {
+                    survival = Some(counts(digits)?);
+                }
+            }
+        } else {
+            // S/B notation
+            survival = Some(counts(parts[0])?);
+            birth = Some(counts(parts[1])?);
+        }
+
+        let (birth, survival) = match (birth, survival) {
+            (Some(birth), Some(survival)) => (birth, survival),
+            _ => anyhow::bail!("'{}' needs both a birth and a survival condition", rule)
+        };
+
+        let states = match parts.get(2) {
+            Some(part) => states(part)?,
+            None => 2
+        };
+
+        anyhow::Ok(Self {
+            birth,
+            survival,
+            states,
+            neighborhood: Neighborhood::default(),
+            middle: false
+        } )
+    }
+}
+
+// Writes the rule back out in B/S notation,
+// or Larger-than-Life notation if it can't be expressed in B/S
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.neighborhood != Neighborhood::default() || self.middle {
+            let ranges = |ranges: &[RangeInclusive<u32>]| ranges
+                .iter()
+                .map(|range| if range.start() == range.end() {
+                    range.start().to_string()
+                } else {
+                    format!("{}..{}", range.start(), range.end())
+                } )
+                .collect::<Vec<_>>()
+                .join(",");
+
+            let (kind, radius) = match self.neighborhood {
+                Neighborhood::Moore { radius } => ('M', radius),
+                Neighborhood::VonNeumann { radius } => ('N', radius),
+                Neighborhood::Circular { radius } => ('C', radius),
+                // Parsed rules never carry a custom neighborhood
+                Neighborhood::Custom(_) |
+                Neighborhood::Weighted(_) |
+                Neighborhood::Margolus => return Err(fmt::Error)
+            };
+
+            return write!(f, "R{},C{},M{},S{},B{},N{}",
+                radius,
+                if self.states > 2 { self.states } else { 0 },
+                u32::from(self.middle),
+                ranges(&self.survival),
+                ranges(&self.birth),
+                kind
+            );
+        }
+
+        let digits = |ranges: &[RangeInclusive<u32>]| ranges
+            .iter()
+            .flat_map(|range| range.clone())
+            .map(|count| count.to_string())
+            .collect::<String>();
+
+        write!(f, "B{}/S{}", digits(&self.birth), digits(&self.survival))?;
+        if self.states > 2 {
+            write!(f, "/{}", self.states)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Builds a WGSL condition that holds when `count` falls in any of the ranges
+fn condition(ranges: &[RangeInclusive<u32>]) -> String {
+    if ranges.is_empty() {
+        return "false".to_string();
+    }
+
+    ranges
+        .iter()
+        .map(|range| if range.start() == range.end() {
+            format!("count == {}u", range.start())
+        } else {
+            format!("(count >= {}u && count <= {}u)", range.start(), range.end())
+        } )
+        .collect::<Vec<_>>()
+        .join(" || ")
+}
+
+impl Rule {
+    pub fn states(&self) -> u32 {
+        self.states
+    }
+
+    // The neighborhood to pair with the rule's state shader
+    pub fn neighborhood(&self) -> Neighborhood {
+        self.neighborhood.clone()
+    }
+
+    // State 1 is alive, states past it are dying (in Generations rules)
+    pub fn state_shader(&self) -> Cow<'static, str> {
+        // Dying cells don't count as neighbors
+        let mut count = if self.states > 2 {
+            "matching(neighborhood, 1u)"
+        } else {
+            "living(neighborhood)"
+        }.to_string();
+
+        if self.middle {
+            count.push_str(" + select(0u, 1u, state == 1u)");
+        }
+
+        format!("
+            fn main(neighborhood: Neighborhood, state: u32) -> u32 {{
+                let count = {};
+
+                if(state == 0u) {{
+                    if({}) {{ return 1u; }}
+                    return 0u;
+                }}
+
+                if(state == 1u && ({})) {{ return 1u; }}
+                if(state >= {}u) {{ return 0u; }}
+
+                return state + 1u;
+            }}",
+            count,
+            condition(&self.birth),
+            condition(&self.survival),
+            self.states - 1
+        ).into()
+    }
+
+    // Living cells are white, dying cells fade from orange to blue
+    pub fn coloring(&self) -> Vec<color::Coloring> {
+        match self.states {
+            0..=2 => vec![color::alive([1.0; 3])],
+            3 => vec![
+                color::map(1, [1.0; 3]),
+                color::map(2, [1.0, 0.2, 0.0])
+            ],
+            states => vec![
+                color::map(1, [1.0; 3]),
+                color::lerp(2..=(states - 1), [1.0, 0.2, 0.0], [0.1, 0.2, 1.0])
+            ]
+        }
+    }
+}
+
+// Lets the CPU backend run the same rule as the generated shader
+impl cpu::Rule for Rule {
+    fn next(&self, neighborhood: &cpu::Neighborhood<'_>, state: u32) -> u32 {
+        let count = if self.states > 2 {
+            neighborhood.matching(1)
+        } else {
+            neighborhood.living()
+        } + u32::from(self.middle && state == 1);
+
+        let any = |ranges: &[RangeInclusive<u32>]| ranges
+            .iter()
+            .any(|range| range.contains(&count));
+
+        if state == 0 {
+            u32::from(any(&self.birth))
+        } else if state == 1 && any(&self.survival) {
+            1
+        } else if state >= self.states - 1 {
+            0
+        } else {
+            state + 1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(rulestring: &str) -> Rule {
+        rulestring.parse().unwrap()
+    }
+
+    #[test]
+    fn either_order_of_birth_and_survival() {
+        for rulestring in ["B3/S23", "S23/B3", "b3/s23", "23/3", " B3 / S23 "] {
+            let life = rule(rulestring);
+
+            assert_eq!(life.birth, [3..=3], "{}", rulestring);
+            assert_eq!(life.survival, [2..=3], "{}", rulestring);
+            assert_eq!(life.states, 2);
+        }
+    }
+
+    #[test]
+    fn generations() {
+        let brain = rule("B2/S/3");
+        assert_eq!((brain.birth, brain.survival, brain.states), (vec![2..=2], vec![], 3));
+
+        // Star Wars, in S/B/C notation
+        let star_wars = rule("345/2/4");
+        assert_eq!((star_wars.birth, star_wars.survival, star_wars.states), (vec![2..=2], vec![3..=5], 4));
+    }
+
+    #[test]
+    fn larger_than_life() {
+        let bosco = rule("R5,C0,M1,S34..58,B34..45,NM");
+
+        assert_eq!(bosco.birth, [34..=45]);
+        assert_eq!(bosco.survival, [34..=58]);
+        assert_eq!(bosco.states, 2);
+        assert!(bosco.middle);
+        assert!(bosco.neighborhood == Neighborhood::Moore { radius: 5 });
+    }
+
+    #[test]
+    fn larger_than_life_counts_stay_within_the_neighborhood() {
+        // 11x11 cells, the middle one included
+        assert!("R5,C0,M1,S34..58,B34..121,NM".parse::<Rule>().is_ok());
+        assert!("R5,C0,M1,S34..58,B34..122,NM".parse::<Rule>().is_err());
+        assert!("R5,C0,M0,S34..121,B34..45,NM".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_rulestrings() {
+        for rulestring in ["", "B3", "B9/S23", "B3/S29", "B3/B23", "S23/S3", "B3/S23/1", "B3/S23/X", "B3/S23/4/5", "R5,C0,M1,S34..58,B34..45,NX"] {
+            assert!(rulestring.parse::<Rule>().is_err(), "{}", rulestring);
+        }
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for (rulestring, written) in [
+            ("B3/S23", "B3/S23"),
+            ("23/3", "B3/S23"),
+            ("B36/S23", "B36/S23"),
+            ("B2/S/3", "B2/S/3"),
+            ("345/2/4", "B2/S345/4"),
+            ("B/S012345678", "B/S012345678"),
+            ("R5,C0,M1,S34..58,B34..45,NM", "R5,C0,M1,S34..58,B34..45,NM"),
+            ("R2,C3,M0,S2..4,B3,NN", "R2,C3,M0,S2..4,B3,NN")
+        ] {
+            assert_eq!(rule(rulestring).to_string(), written);
+            assert_eq!(rule(written).to_string(), written);
+        }
+    }
+}
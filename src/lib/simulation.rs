@@ -0,0 +1,187 @@
+use crate::{
+    automata,
+    backend::Backend,
+    camera,
+    compute,
+    state,
+    Config,
+    Gridlines
+};
+
+// A windowed simulation that can be driven from inside another application's event loop
+pub struct Simulation {
+    pub(crate) state: state::State
+}
+
+impl Simulation {
+    pub async fn new(
+        window: &winit::window::Window,
+        automata: automata::Automata,
+        config: &Config<'_>
+    ) -> anyhow::Result<Self> {
+        let shader_descriptor = compute::shader(config, automata.size)?;
+
+        let state = state::State::new(
+            window,
+            shader_descriptor,
+            automata,
+            config.topology,
+            config.gridlines
+        ).await?;
+
+        anyhow::Ok(Self { state })
+    }
+
+    pub fn device(&self) -> &wgpu::Device {
+        &self.state.device
+    }
+
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.state.queue
+    }
+
+    // Any view passed to `render_to` must use this format
+    pub fn texture_format(&self) -> wgpu::TextureFormat {
+        self.state.surface_config.format
+    }
+
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.state.resize(new_size);
+    }
+
+    // Draws the current generation to the window's surface
+    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        self.state.render()
+    }
+
+    // Draws the current generation to a texture owned by the caller
+    pub fn render_to(&self, view: &wgpu::TextureView) {
+        self.state.render_to(view);
+    }
+
+    // The colors of the current generation, as drawn in the window, as 8-bit RGBA rows
+    pub fn read_frame(&self) -> Option<Vec<u8>> {
+        self.state.compute.read_frame(&self.state.device, &self.state.queue)
+    }
+
+    pub fn read_cells(&self) -> &automata::Automata {
+        &self.state.automata
+    }
+
+    // Replaces the cells of the current generation,
+    // the new automata must have the same dimensions as the old one
+    pub fn write_cells(&mut self, automata: automata::Automata) -> anyhow::Result<()> {
+        let (width, height) = (self.state.automata.size.width, self.state.automata.size.height);
+        if automata.size.width != width || automata.size.height != height {
+            anyhow::bail!(
+                "expected a {}x{} automata, found {}x{}",
+                width, height, automata.size.width, automata.size.height
+            );
+        }
+
+        self.state.compute.write(&self.state.device, &self.state.queue, 0, &automata.data);
+        self.state.automata = automata;
+
+        anyhow::Ok(())
+    }
+
+    // The cell under a position in the window, like the cursor's, if there is one
+    // On a hexagonal grid this is always a `Cell::Hex`
+    pub fn cell_at(&self, position: winit::dpi::PhysicalPosition<f64>) -> Option<automata::Cell> {
+        let tex = self.state.camera.tex(self.state.physical_size, self.state.extent(), position);
+
+        self.state.topology.cell_at(self.state.automata.size, tex)
+    }
+
+    // Zooms the camera in by `factor` (or out, below 1), keeping whatever is under `position` in place
+    pub fn zoom(&mut self, position: winit::dpi::PhysicalPosition<f64>, factor: f32) {
+        let extent = self.state.extent();
+        self.state.camera.zoom(self.state.physical_size, extent, position, factor);
+        self.state.update_camera();
+    }
+
+    // Moves the camera along with a drag of `delta` pixels
+    pub fn pan(&mut self, delta: (f64, f64)) {
+        let extent = self.state.extent();
+        self.state.camera.pan(self.state.physical_size, extent, delta);
+        self.state.update_camera();
+    }
+
+    // Fits the whole field to the window, and keeps it fitted as the window is resized
+    pub fn fit_to_window(&mut self) {
+        self.state.camera = camera::Camera::fit(self.state.physical_size, self.state.extent());
+        self.state.update_camera();
+    }
+
+    // Sets every given cell of the current generation to `state`, skipping any outside the field
+    // Cells can be given by column and row as a `Point2`, or as a `Hex` on a hexagonal grid
+    // Only the stretch of cells between the first and last change is written to the GPU
+    pub fn paint<C, I>(&mut self, cells: I, state: u32)
+    where
+        C: Into<automata::Cell>,
+        I: IntoIterator<Item = C>
+    {
+        let size = self.state.automata.size;
+
+        let mut changed: Option<(usize, usize)> = None;
+        for cell in cells {
+            let cell = match cell.into().position(size) {
+                Some(cell) => cell,
+                None => continue
+            };
+
+            self.state.automata[cell] = state;
+
+            let index = (cell.x + cell.y * size.width) as usize;
+            changed = Some(changed.map_or((index, index), |(first, last)| (first.min(index), last.max(index))));
+        }
+
+        if let Some((first, last)) = changed {
+            let data = &self.state.automata.data[first..=last];
+            self.state.compute.write(&self.state.device, &self.state.queue, first, data);
+        }
+    }
+
+    // Replaces the gridlines, showing them if they're `Some` and hiding them otherwise
+    pub fn set_gridlines(&mut self, gridlines: Option<Gridlines>) {
+        self.state.gridlines_visible = gridlines.is_some();
+        if let Some(gridlines) = gridlines {
+            self.state.gridlines = gridlines;
+        }
+
+        self.state.update_gridlines();
+    }
+
+    // Shows or hides the gridlines, keeping their last settings
+    pub fn toggle_gridlines(&mut self) {
+        self.state.gridlines_visible = !self.state.gridlines_visible;
+        self.state.update_gridlines();
+    }
+
+    // Draws text in the window's top-left corner, over the field, until it's replaced or `None`
+    pub fn set_overlay(&mut self, text: Option<&str>) {
+        self.state.overlay.set(&self.state.device, &self.state.queue, text);
+    }
+
+    pub fn into_automata(self) -> automata::Automata {
+        self.state.automata
+    }
+}
+
+impl Backend for Simulation {
+    fn automata(&self) -> &automata::Automata {
+        self.read_cells()
+    }
+
+    fn generation(&self) -> u64 {
+        self.state.compute.generation
+    }
+
+    fn set_generation(&mut self, generation: u64) {
+        self.state.compute.generation = generation;
+    }
+
+    fn step_n(&mut self, generations: u32) {
+        self.state.tick(generations);
+    }
+}
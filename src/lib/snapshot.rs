@@ -0,0 +1,407 @@
+use std::{
+    collections,
+    fmt::Write,
+    fs,
+    path
+};
+
+use crate::{
+    automata,
+    backend::Backend,
+    color::{ self, Coloring },
+    Boundary,
+    Config,
+    Neighborhood,
+    Topology
+};
+
+const MAGIC: &str = "gridded_automata snapshot";
+const VERSION: u32 = 1;
+
+// Everything needed to pick a run back up where it was paused:
+// the cells, the generation they're at, and the parts of the `Config` that shape the simulation
+#[derive(Clone)]
+pub struct Snapshot {
+    pub automata: automata::Automata,
+    pub generation: u64,
+    pub title: Option<String>,
+    pub fps: u32,
+    pub state_shader: String,
+    pub coloring: Vec<Coloring>,
+    pub neighborhood: Neighborhood,
+    pub boundary: Boundary,
+    pub topology: Topology
+}
+
+fn coloring(coloring: &[Coloring]) -> String {
+    let color = |c: &[f32; 3]| format!("{:?} {:?} {:?}", c[0], c[1], c[2]);
+
+    coloring
+        .iter()
+        .map(|coloring| match &coloring.rule {
+            color::Rule::Lerp(range, start, end) => {
+                format!("lerp {} {} {} {}", range.start(), range.end(), color(start), color(end))
+            },
+            color::Rule::Alive(c) => format!("alive {}", color(c)),
+            color::Rule::Map(state, c) => format!("map {} {}", state, color(c)),
+            color::Rule::MapRange(range, c) => {
+                format!("map_range {} {} {}", range.start(), range.end(), color(c))
+            }
+        } + "\n")
+        .collect()
+}
+
+fn parse_coloring(section: &str) -> anyhow::Result<Vec<Coloring>> {
+    section
+        .lines()
+        .map(|line| {
+            let mut words = line.split_whitespace();
+            let kind = words.next().unwrap_or_default();
+            let values = words.collect::<Vec<_>>();
+
+            let state = |i: usize| anyhow::Ok(values.get(i).copied().unwrap_or_default().parse::<u32>()?);
+            let color = |i: usize| -> anyhow::Result<[f32; 3]> {
+                let mut color = [0.0; 3];
+                for (c, value) in color.iter_mut().zip(i..i + 3) {
+                    *c = values.get(value).copied().unwrap_or_default().parse::<f32>()?;
+                }
+
+                anyhow::Ok(color)
+            };
+
+            anyhow::Ok(match kind {
+                "lerp" => color::lerp(state(0)?..=state(1)?, color(2)?, color(5)?),
+                "alive" => color::alive(color(0)?),
+                "map" => color::map(state(0)?, color(1)?),
+                "map_range" => color::map_range(state(0)?..=state(1)?, color(2)?),
+                _ => anyhow::bail!("'{}' is not a coloring", line)
+            } )
+        } )
+        .collect()
+}
+
+fn neighborhood(neighborhood: &Neighborhood) -> String {
+    match neighborhood {
+        Neighborhood::Moore { radius } => format!("moore {}", radius),
+        Neighborhood::VonNeumann { radius } => format!("von_neumann {}", radius),
+        Neighborhood::Circular { radius } => format!("circular {}", radius),
+        Neighborhood::Custom(offsets) => offsets
+            .iter()
+            .fold("custom".to_string(), |s, (dx, dy)| s + &format!(" {},{}", dx, dy)),
+        Neighborhood::Weighted(weights) => weights
+            .iter()
+            .fold("weighted".to_string(), |s, ((dx, dy), w)| s + &format!(" {},{},{:?}", dx, dy, w)),
+        Neighborhood::Margolus => "margolus".to_string()
+    }
+}
+
+fn parse_neighborhood(section: &str) -> anyhow::Result<Neighborhood> {
+    let mut words = section.split_whitespace();
+    let kind = words.next().unwrap_or_default();
+    let values = words.map(|word| word.split(',').collect::<Vec<_>>()).collect::<Vec<_>>();
+
+    let radius = || anyhow::Ok(values.first().and_then(|v| v.first()).unwrap_or(&"").parse::<u32>()?);
+    let offset = |value: &[&str]| anyhow::Ok((value[0].parse::<i32>()?, value[1].parse::<i32>()?));
+
+    anyhow::Ok(match kind {
+        "moore" => Neighborhood::Moore { radius: radius()? },
+        "von_neumann" => Neighborhood::VonNeumann { radius: radius()? },
+        "circular" => Neighborhood::Circular { radius: radius()? },
+        "custom" if values.iter().all(|value| value.len() == 2) => Neighborhood::Custom(
+            values.iter().map(|value| offset(value)).collect::<anyhow::Result<_>>()?
+        ),
+        "weighted" if values.iter().all(|value| value.len() == 3) => Neighborhood::Weighted(
+            values
+                .iter()
+                .map(|value| anyhow::Ok((offset(value)?, value[2].parse::<f32>()?)))
+                .collect::<anyhow::Result<_>>()?
+        ),
+        "margolus" => Neighborhood::Margolus,
+        _ => anyhow::bail!("'{}' is not a neighborhood", section)
+    } )
+}
+
+fn boundary(boundary: Boundary) -> String {
+    match boundary {
+        Boundary::Torus => "torus".to_string(),
+        Boundary::Constant(state) => format!("constant {}", state),
+        Boundary::Reflect => "reflect".to_string(),
+        Boundary::Cylinder => "cylinder".to_string(),
+        Boundary::KleinBottle => "klein_bottle".to_string()
+    }
+}
+
+fn parse_boundary(section: &str) -> anyhow::Result<Boundary> {
+    anyhow::Ok(match section.split_whitespace().collect::<Vec<_>>()[..] {
+        ["torus"] => Boundary::Torus,
+        ["constant", state] => Boundary::Constant(state.parse::<u32>()?),
+        ["reflect"] => Boundary::Reflect,
+        ["cylinder"] => Boundary::Cylinder,
+        ["klein_bottle"] => Boundary::KleinBottle,
+        _ => anyhow::bail!("'{}' is not a boundary", section)
+    } )
+}
+
+// Dimensions followed by every cell, as little-endian `u32`s
+fn cells(automata: &automata::Automata) -> Vec<u8> {
+    [automata.size.width, automata.size.height]
+        .iter()
+        .chain(automata.data.iter())
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+fn parse_cells(section: &[u8]) -> anyhow::Result<automata::Automata> {
+    let values = section
+        .chunks_exact(4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+        .collect::<Vec<_>>();
+
+    let (size, data) = match values[..] {
+        [width, height, ref data @ ..] => (automata::Size { width, height }, data),
+        _ => anyhow::bail!("the cells are missing their dimensions")
+    };
+
+    if section.len() % 4 != 0 || data.len() as u64 != size.width as u64 * size.height as u64 {
+        anyhow::bail!("the cells don't match their {}x{} dimensions", size.width, size.height);
+    }
+
+    let mut automata = automata::Automata::new(size);
+    automata.data = data.to_vec();
+
+    anyhow::Ok(automata)
+}
+
+impl Snapshot {
+    pub fn new<B: Backend>(backend: &B, config: &Config<'_>) -> Self {
+        Self {
+            automata: backend.automata().clone(),
+            generation: backend.generation(),
+            title: config.title.as_ref().map(|title| title.to_string()),
+            fps: config.fps,
+            state_shader: config.state_shader.to_string(),
+            coloring: config.coloring.to_vec(),
+            neighborhood: config.neighborhood.clone(),
+            boundary: config.boundary,
+            topology: config.topology
+        }
+    }
+
+    // A `Config` that sets the simulation up the same way again
+    // Backends start counting from zero, so follow up with `Backend::set_generation`
+    pub fn config(&self) -> Config<'_> {
+        Config {
+            title: self.title.clone().map(Into::into),
+            fps: self.fps,
+            state_shader: self.state_shader.clone().into(),
+            coloring: &self.coloring,
+            neighborhood: self.neighborhood.clone(),
+            boundary: self.boundary,
+            topology: self.topology,
+            ..Default::default()
+        }
+    }
+
+    // The file starts with a versioned header line,
+    // followed by sections that each give their name and length in bytes
+    pub fn save<P: AsRef<path::Path>>(&self, file: P) -> anyhow::Result<()> {
+        let mut sections: Vec<(&str, Vec<u8>)> = vec![
+            ("generation", self.generation.to_string().into_bytes()),
+            ("fps", self.fps.to_string().into_bytes()),
+            ("neighborhood", neighborhood(&self.neighborhood).into_bytes()),
+            ("boundary", boundary(self.boundary).into_bytes()),
+            ("topology", match self.topology {
+                Topology::Square => "square",
+                Topology::Hexagonal => "hexagonal"
+            }.into()),
+            ("coloring", coloring(&self.coloring).into_bytes()),
+            ("state_shader", self.state_shader.clone().into_bytes()),
+            ("cells", cells(&self.automata))
+        ];
+
+        if let Some(title) = &self.title {
+            sections.insert(0, ("title", title.clone().into_bytes()));
+        }
+
+        let mut header = String::new();
+        writeln!(header, "{} {}", MAGIC, VERSION)?;
+
+        let mut bytes = header.into_bytes();
+        for (name, section) in sections {
+            bytes.extend(format!("{} {}\n", name, section.len()).bytes());
+            bytes.extend(section);
+            bytes.push(b'\n');
+        }
+
+        anyhow::Ok(fs::write(file, bytes)?)
+    }
+
+    pub fn load<P: AsRef<path::Path>>(file: P) -> anyhow::Result<Self> {
+        let bytes = fs::read(file)?;
+
+        // Splits off everything up to the next newline
+        let line = |bytes: &[u8]| -> anyhow::Result<(String, usize)> {
+            let end = bytes
+                .iter()
+                .position(|&byte| byte == b'\n')
+                .ok_or_else(|| anyhow::anyhow!("the snapshot ends in the middle of a line"))?;
+
+            anyhow::Ok((String::from_utf8(bytes[..end].to_vec())?, end + 1))
+        };
+
+        let (header, mut position) = line(&bytes)?;
+        let version = header
+            .strip_prefix(MAGIC)
+            .and_then(|version| version.trim().parse::<u32>().ok())
+            .ok_or_else(|| anyhow::anyhow!("the file is not a snapshot"))?;
+
+        if version > VERSION {
+            anyhow::bail!("snapshot version {} is newer than the {} this library reads", version, VERSION);
+        }
+
+        let mut sections = collections::HashMap::new();
+        while position < bytes.len() {
+            let (section, length) = line(&bytes[position..])?;
+            position += length;
+
+            let (name, size) = section
+                .split_once(' ')
+                .and_then(|(name, size)| Some((name.to_string(), size.parse::<usize>().ok()?)))
+                .ok_or_else(|| anyhow::anyhow!("'{}' is not a section header", section))?;
+
+            let end = position
+                .checked_add(size)
+                .filter(|&end| end < bytes.len())
+                .ok_or_else(|| anyhow::anyhow!("the '{}' section runs past the end of the file", name))?;
+
+            sections.insert(name, &bytes[position..end]);
+            position = end + 1;
+        }
+
+        // Sections this version doesn't know about are skipped
+        let text = |name: &str| -> anyhow::Result<&str> {
+            let section = sections
+                .get(name)
+                .ok_or_else(|| anyhow::anyhow!("the snapshot has no '{}' section", name))?;
+
+            anyhow::Ok(std::str::from_utf8(section)?)
+        };
+
+        anyhow::Ok(Self {
+            automata: parse_cells(sections.get("cells").copied().unwrap_or_default())?,
+            generation: text("generation")?.parse::<u64>()?,
+            title: text("title").ok().map(str::to_string),
+            fps: text("fps")?.parse::<u32>()?,
+            state_shader: text("state_shader")?.to_string(),
+            coloring: parse_coloring(text("coloring")?)?,
+            neighborhood: parse_neighborhood(text("neighborhood")?)?,
+            boundary: parse_boundary(text("boundary")?)?,
+            topology: match text("topology")? {
+                "square" => Topology::Square,
+                "hexagonal" => Topology::Hexagonal,
+                topology => anyhow::bail!("'{}' is not a topology", topology)
+            }
+        } )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A file of its own in the temp directory, so tests running in parallel don't collide
+    fn file(name: &str) -> path::PathBuf {
+        std::env::temp_dir().join(format!("gridded_automata_{}_{}.snapshot", name, std::process::id()))
+    }
+
+    fn snapshot() -> Snapshot {
+        let mut automata = automata::Automata::new((3, 2).into());
+        automata.data = vec![0, 1, 2, 3, 4, u32::MAX];
+
+        Snapshot {
+            automata,
+            generation: u64::MAX - 1,
+            title: Some("Wire\nWorld".to_string()),
+            fps: 30,
+            state_shader: "fn state() {}\n".to_string(),
+            coloring: vec![
+                color::lerp(1..=6, [1.0, 0.2, 0.0], [0.1, 0.2, 1.0]),
+                color::alive([0.5, 0.25, 0.125]),
+                color::map(7, [0.0, 1.0, 0.0]),
+                color::map_range(8..=9, [0.3, 0.3, 0.3])
+            ],
+            neighborhood: Neighborhood::Weighted(vec![((-1, 0), 0.5), ((2, -3), 1.25)]),
+            boundary: Boundary::Constant(4),
+            topology: Topology::Hexagonal
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let file = file("round_trip");
+        let saved = snapshot();
+        saved.save(&file).unwrap();
+
+        let loaded = Snapshot::load(&file).unwrap();
+        fs::remove_file(&file).unwrap();
+
+        assert_eq!(loaded.automata.data, saved.automata.data);
+        assert_eq!((loaded.automata.size.width, loaded.automata.size.height), (3, 2));
+        assert_eq!(loaded.generation, saved.generation);
+        assert_eq!(loaded.title, saved.title);
+        assert_eq!(loaded.fps, saved.fps);
+        assert_eq!(loaded.state_shader, saved.state_shader);
+        assert_eq!(coloring(&loaded.coloring), coloring(&saved.coloring));
+        assert!(loaded.neighborhood == saved.neighborhood);
+        assert_eq!(boundary(loaded.boundary), boundary(saved.boundary));
+        assert!(matches!(loaded.topology, Topology::Hexagonal));
+    }
+
+    #[test]
+    fn untitled_round_trip() {
+        let file = file("untitled");
+        let saved = Snapshot { title: None, neighborhood: Neighborhood::Custom(vec![(0, -1), (1, 1)]), ..snapshot() };
+        saved.save(&file).unwrap();
+
+        let loaded = Snapshot::load(&file).unwrap();
+        fs::remove_file(&file).unwrap();
+
+        assert_eq!(loaded.title, None);
+        assert!(loaded.neighborhood == saved.neighborhood);
+    }
+
+    #[test]
+    fn skips_unknown_sections() {
+        let file = file("unknown_sections");
+        snapshot().save(&file).unwrap();
+
+        let mut bytes = fs::read(&file).unwrap();
+        let header = bytes.iter().position(|&byte| byte == b'\n').unwrap() + 1;
+        bytes.splice(header..header, b"future 3\nabc\n".iter().copied());
+        fs::write(&file, bytes).unwrap();
+
+        let loaded = Snapshot::load(&file);
+        fs::remove_file(&file).unwrap();
+
+        assert_eq!(loaded.unwrap().generation, u64::MAX - 1);
+    }
+
+    #[test]
+    fn rejects_newer_versions_and_truncation() {
+        let file = file("rejects");
+        snapshot().save(&file).unwrap();
+        let bytes = fs::read(&file).unwrap();
+
+        let newer = format!("{} {}", MAGIC, VERSION + 1).into_bytes();
+        let header = bytes.iter().position(|&byte| byte == b'\n').unwrap();
+        fs::write(&file, [&newer[..], &bytes[header..]].concat()).unwrap();
+        let newer = Snapshot::load(&file);
+
+        fs::write(&file, &bytes[..bytes.len() - 8]).unwrap();
+        let truncated = Snapshot::load(&file);
+        fs::remove_file(&file).unwrap();
+
+        assert!(newer.is_err());
+        assert!(truncated.is_err());
+    }
+}
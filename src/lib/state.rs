@@ -1,44 +1,49 @@
-use std::{
-    iter,
-    mem,
-    sync::{ Arc, Mutex },
-    cell::Cell
-};
+use std::iter;
 
 use wgpu::util::DeviceExt;
 
 use crate::{
     Vertex, 
     CLIP_SPACE_EXTREMA,
-    automata
+    automata,
+    camera,
+    compute,
+    hud,
+    Gridlines,
+    Topology
 };
 
 pub(crate) struct State {
     pub(crate) automata: automata::Automata,
+    pub(crate) topology: Topology,
 
     pub(crate) physical_size: winit::dpi::PhysicalSize<u32>,
     pub(crate) device: wgpu::Device,
     pub(crate) surface: wgpu::Surface,
     pub(crate) surface_config: wgpu::SurfaceConfiguration,
     pub(crate) queue: wgpu::Queue,
-    pub(crate) size_group: wgpu::BindGroup,
-    pub(crate) cell_buffers: (wgpu::Buffer, wgpu::Buffer),
-    pub(crate) cell_groups: (wgpu::BindGroup, wgpu::BindGroup),
-    pub(crate) compute_texture_group: wgpu::BindGroup,
-    pub(crate) compute_pipeline: wgpu::ComputePipeline,
-    pub(crate) workgroup: u32,
+    pub(crate) compute: compute::Compute,
     pub(crate) vertex_buffer: wgpu::Buffer,
     pub(crate) index_buffer: wgpu::Buffer,
     pub(crate) render_texture_group: wgpu::BindGroup,
+    pub(crate) camera: camera::Camera,
+    pub(crate) camera_buffer: wgpu::Buffer,
+    pub(crate) gridlines: Gridlines,
+    pub(crate) gridlines_visible: bool,
+    pub(crate) gridlines_buffer: wgpu::Buffer,
+    pub(crate) camera_group: wgpu::BindGroup,
     pub(crate) render_pipeline: wgpu::RenderPipeline,
+    pub(crate) overlay: hud::Overlay,
 }
 
 impl State {
     pub(crate) async fn new(
         window: &winit::window::Window, 
         shader_descriptor: wgpu::ShaderModuleDescriptor<'static>,
-        automata: automata::Automata
-    ) -> Self {
+        automata: automata::Automata,
+        topology: Topology,
+        gridlines: Option<Gridlines>
+    ) -> anyhow::Result<Self> {
         //
         // WGPU Mandatory State Information
         //
@@ -55,7 +60,7 @@ impl State {
                 compatible_surface: Some(&surface),
                 force_fallback_adapter: false,
             }
-        ).await.unwrap();
+        ).await.ok_or_else(|| anyhow::anyhow!("no suitable adapter was found"))?;
 
         let (device, queue) = adapter.request_device(
             &wgpu::DeviceDescriptor {
@@ -64,7 +69,7 @@ impl State {
                 limits: wgpu::Limits::default(),
             },
             None
-        ).await.unwrap();
+        ).await?;
 
         let surface_config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
@@ -89,257 +94,106 @@ impl State {
         );
 
         //
-        // DIMENSION BUFFER AND BIND GROUPS
+        // COMPUTE SHADER
         //
 
-        let size_buffer = device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: None,
-                contents: bytemuck::cast_slice(&[automata.size]),
-                usage: wgpu::BufferUsages::UNIFORM,
-            }
-        );
+        let compute = compute::Compute::new(&device, shader_descriptor, &automata);
 
-        let size_group_layout = device.create_bind_group_layout(
-            &wgpu::BindGroupLayoutDescriptor {
-                label: None,
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::all(),
-                    count: None,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    }
-                }]
-            }
-        );
-
-        let size_group = device.create_bind_group(
-            &wgpu::BindGroupDescriptor {
-                label: None,
-                layout: &size_group_layout,
-                entries: &[wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: size_buffer.as_entire_binding()
-                }]
-            }
-        );
-
-        // 
-        // OUTPUT TEXTURE CREATION
         //
-
-        let extent = wgpu::Extent3d {
-            width: automata.size.width,
-            height: automata.size.height,
-            depth_or_array_layers: 1,
-        };
-
-        let texture = device.create_texture(
-            &wgpu::TextureDescriptor {
-                label: None,
-                size: extent,
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba32Float,
-                usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING,
-            }
-        );
-
-        let texture_view = texture.create_view(
-            &wgpu::TextureViewDescriptor {
-                label: None,
-                format: Some(wgpu::TextureFormat::Rgba32Float),
-                dimension: Some(wgpu::TextureViewDimension::D2),
-                aspect: wgpu::TextureAspect::All,
-                base_mip_level: 0,
-                mip_level_count: std::num::NonZeroU32::new(1),
-                base_array_layer: 0,
-                array_layer_count: std::num::NonZeroU32::new(1),
-                
-            }
-        );
-
-        //
-        // COMPUTE SHADER
+        // RENDER SHADER
         //
 
-        let cell_buffers = (
-            device.create_buffer_init(
-                &wgpu::util::BufferInitDescriptor {
-                    label: None,
-                    contents: &{
-                        automata.data
-                            .iter()
-                            .map(|x| u32::to_ne_bytes(*x)) 
-                            .flat_map(|f| f.into_iter())
-                            .collect::<Vec<_>>()
-                    },
-                    usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::MAP_READ
-                }
-            ), 
-            device.create_buffer(
-                &wgpu::BufferDescriptor {
-                    label: None,
-                    size: (automata.data.len() * 4) as wgpu::BufferAddress,
-                    usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::MAP_READ,
-                    mapped_at_creation: false,
-                }  
-            )
-        );
-
-        let cell_group_layout = device.create_bind_group_layout(
-            &wgpu::BindGroupLayoutDescriptor {
+        let vertex_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
                 label: None,
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        count: None,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::Buffer {
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                            ty: wgpu::BufferBindingType::Storage { read_only: true },
-                        }
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        count: None,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::Buffer {
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                            ty: wgpu::BufferBindingType::Storage { read_only: false },
-                        }
-                    }
-                ],
+                contents: bytemuck::cast_slice(CLIP_SPACE_EXTREMA.as_slice()),
+                usage: wgpu::BufferUsages::VERTEX
             }
         );
 
-        let cell_groups = (
-            device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: None,
-                layout: &cell_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: cell_buffers.0.as_entire_binding()
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: cell_buffers.1.as_entire_binding()
-                    }
-                ]
-            } ),
-            device.create_bind_group(&wgpu::BindGroupDescriptor {
+        let index_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
                 label: None,
-                layout: &cell_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: cell_buffers.1.as_entire_binding()
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: cell_buffers.0.as_entire_binding()
-                    }
-                ]
-            } )
+                contents: bytemuck::cast_slice([0, 1, 3, 0, 3, 2].as_slice()),
+                usage: wgpu::BufferUsages::INDEX
+            }
         );
 
-        let compute_texture_group_layout = device.create_bind_group_layout(
+        let render_texture_group_layout = device.create_bind_group_layout(
             &wgpu::BindGroupLayoutDescriptor {
                 label: None,
                 entries: &[
                     wgpu::BindGroupLayoutEntry {
                         binding: 0,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::StorageTexture {
-                            access: wgpu::StorageTextureAccess::WriteOnly,
-                            format: wgpu::TextureFormat::Rgba32Float,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
                             view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
                         },
-                        count: None,
+                        count: None
                     }
                 ],
             }
         );
 
-        let compute_texture_group = device.create_bind_group(
+        let render_texture_group = device.create_bind_group(
             &wgpu::BindGroupDescriptor {
                 label: None,
-                layout: &compute_texture_group_layout,
+                layout: &render_texture_group_layout,
                 entries: &[
                     wgpu::BindGroupEntry {
                         binding: 0,
-                        resource: wgpu::BindingResource::TextureView(&texture_view),
+                        resource: wgpu::BindingResource::TextureView(&compute.texture_view),
                     }
                 ],
             }
         );
 
-        let compute_pipeline = device.create_compute_pipeline(
-            &wgpu::ComputePipelineDescriptor {
-                label: None,
-                layout: Some(&{
-                    device.create_pipeline_layout(
-                        &wgpu::PipelineLayoutDescriptor {
-                            label: None,
-                            push_constant_ranges: &[],
-                            bind_group_layouts: &[
-                                &size_group_layout, 
-                                &cell_group_layout, 
-                                &compute_texture_group_layout
-                            ]
-                        } 
-                    )
-                } ),
-                module: &device.create_shader_module(shader_descriptor),
-                entry_point: "main_cs",
-            }
-        );
-
-        let mut workgroup = 1u32;
-        for i in 2..=16u32 {
-            if automata.size.width % i == 0 && automata.size.height % i == 0 {
-                workgroup = i;
-            }
-        }
-
-        //
-        // RENDER SHADER
-        //
+        // The whole field fits the window until the camera is moved
+        let camera = camera::Camera::fit(physical_size, topology.extent(automata.size));
 
-        let vertex_buffer = device.create_buffer_init(
+        let camera_buffer = device.create_buffer_init(
             &wgpu::util::BufferInitDescriptor {
                 label: None,
-                contents: bytemuck::cast_slice(CLIP_SPACE_EXTREMA.as_slice()),
-                usage: wgpu::BufferUsages::VERTEX
+                contents: bytemuck::cast_slice(&[camera.uniform(physical_size, topology.extent(automata.size))]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
             }
         );
 
-        let index_buffer = device.create_buffer_init(
+        let gridlines_visible = gridlines.is_some();
+        let gridlines = gridlines.unwrap_or_default();
+
+        let gridlines_buffer = device.create_buffer_init(
             &wgpu::util::BufferInitDescriptor {
                 label: None,
-                contents: bytemuck::cast_slice([0, 1, 3, 0, 3, 2].as_slice()),
-                usage: wgpu::BufferUsages::INDEX
+                contents: bytemuck::cast_slice(&[camera::GridUniform::new(&gridlines, gridlines_visible)]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
             }
         );
 
-        let render_texture_group_layout = device.create_bind_group_layout(
+        // The camera and gridlines decide how the field is drawn, but not what's in it
+        let camera_group_layout = device.create_bind_group_layout(
             &wgpu::BindGroupLayoutDescriptor {
                 label: None,
                 entries: &[
                     wgpu::BindGroupLayoutEntry {
                         binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
                         visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            multisampled: false,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
                         },
                         count: None
                     }
@@ -347,14 +201,18 @@ impl State {
             }
         );
 
-        let render_texture_group = device.create_bind_group(
+        let camera_group = device.create_bind_group(
             &wgpu::BindGroupDescriptor {
                 label: None,
-                layout: &render_texture_group_layout,
+                layout: &camera_group_layout,
                 entries: &[
                     wgpu::BindGroupEntry {
                         binding: 0,
-                        resource: wgpu::BindingResource::TextureView(&texture_view),
+                        resource: camera_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: gridlines_buffer.as_entire_binding(),
                     }
                 ],
             }
@@ -369,8 +227,9 @@ impl State {
                             label: None,
                             push_constant_ranges: &[],
                             bind_group_layouts: &[
-                                &size_group_layout, 
-                                &render_texture_group_layout
+                                &compute.size_group_layout, 
+                                &render_texture_group_layout,
+                                &camera_group_layout
                             ]
                         }
                     )
@@ -382,7 +241,10 @@ impl State {
                 },
                 fragment: Some(wgpu::FragmentState {
                     module: &shader,
-                    entry_point: "fs_main",
+                    entry_point: match topology {
+                        Topology::Square => "fs_main",
+                        Topology::Hexagonal => "fs_hex"
+                    },
                     targets: &[Some(wgpu::ColorTargetState {
                         format: surface_config.format,
                         blend: Some(wgpu::BlendState {
@@ -411,24 +273,29 @@ impl State {
             }
         );
 
-        Self {
+        let overlay = hud::Overlay::new(&device, surface_config.format);
+
+        anyhow::Ok(Self {
             automata,
+            topology,
             physical_size,
             device,
             surface,
             surface_config,
             queue,
-            size_group,
-            cell_buffers,
-            cell_groups,
-            compute_texture_group,
-            compute_pipeline,
-            workgroup,
+            compute,
             vertex_buffer,
             index_buffer,
             render_texture_group,
-            render_pipeline
-        }
+            camera,
+            camera_buffer,
+            gridlines,
+            gridlines_visible,
+            gridlines_buffer,
+            camera_group,
+            render_pipeline,
+            overlay
+        } )
     }
 
     pub(crate) fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
@@ -437,66 +304,35 @@ impl State {
             self.surface_config.width = new_size.width;
             self.surface_config.height = new_size.height;
             self.surface.configure(&self.device, &self.surface_config);
-        }
-    }
-
-    pub(crate) fn tick(&mut self) {
-        let desc = wgpu::CommandEncoderDescriptor { label: None };
-        let mut encoder = self.device.create_command_encoder(&desc);
-
-        {
-            let desc = wgpu::ComputePassDescriptor { label: None };
-            let mut compute_pass = encoder.begin_compute_pass(&desc);
 
-            // Access to dimensions, cell arrays and...
-            compute_pass.set_bind_group(0, &self.size_group, &[]);
-            compute_pass.set_bind_group(1, &self.cell_groups.0, &[]);
+            if self.camera.fitted {
+                self.camera = camera::Camera::fit(new_size, self.extent());
+            }
 
-            // ...output texture for the render attachment
-            compute_pass.set_bind_group(2, &self.compute_texture_group, &[]);
+            self.update_camera();
+        }
+    }
 
-            compute_pass.set_pipeline(&self.compute_pipeline);
+    // The field's natural size, which the camera's zoom is measured against
+    pub(crate) fn extent(&self) -> (f32, f32) {
+        self.topology.extent(self.automata.size)
+    }
 
-            compute_pass.dispatch_workgroups(
-                self.automata.size.width / self.workgroup, 
-                self.automata.size.height / self.workgroup, 
-                1
-            );
-        }
+    pub(crate) fn update_gridlines(&self) {
+        let uniform = camera::GridUniform::new(&self.gridlines, self.gridlines_visible);
+        self.queue.write_buffer(&self.gridlines_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
 
-        // Wait for GPU to finish
-        self.queue.submit(Some(encoder.finish()));
-
-        // Get the updated buffer's data as a slice
-        let buffer_slice = self.cell_buffers.1.slice(..);
-
-        // Wait for the callback from map_async before proceeding
-        let ready = Arc::new(Mutex::new(Cell::new(false)));
-        let ready_ref = Arc::clone(&ready);
-        buffer_slice.map_async(wgpu::MapMode::Read, move |_| { 
-            ready_ref.lock().unwrap().set(true);
-        } );
-
-        // Wait until resources are cleaned up
-        self.device.poll(wgpu::Maintain::Wait);
-
-        // Read the cell data from the buffer slice
-        if ready.lock().unwrap().get() {
-            let data = buffer_slice.get_mapped_range();
-            let result = data
-                .chunks_exact(4)
-                .map(|b| u32::from_ne_bytes(b.try_into().unwrap()))
-                .collect::<Vec<_>>();
-            
-            drop(data);
-            self.cell_buffers.1.unmap();
+    // Uploads the camera, after it's been moved or the window resized
+    pub(crate) fn update_camera(&self) {
+        let uniform = self.camera.uniform(self.physical_size, self.extent());
+        self.queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
 
-            self.automata.data = result;
+    pub(crate) fn tick(&mut self, generations: u32) {
+        if let Some(data) = self.compute.step_n(&self.device, &self.queue, generations) {
+            self.automata.data = data;
         }
-
-        // Swap the `current` and `updated` cell arrays for the next gen
-        mem::swap(&mut self.cell_buffers.0, &mut self.cell_buffers.1);
-        mem::swap(&mut self.cell_groups.0, &mut self.cell_groups.1);
     }
     
     pub(crate) fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
@@ -505,6 +341,15 @@ impl State {
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
 
+        self.render_to(&view);
+        
+        output.present();
+
+        Ok(())
+    }
+
+    // Draws the field into any view that shares the surface's texture format
+    pub(crate) fn render_to(&self, view: &wgpu::TextureView) {
         let desc = wgpu::CommandEncoderDescriptor { label: None };
         let mut encoder = self.device.create_command_encoder(&desc);
     
@@ -514,7 +359,7 @@ impl State {
                     label: None,
                     color_attachments: &[
                         Some(wgpu::RenderPassColorAttachment {
-                            view: &view,
+                            view,
                             resolve_target: None,
                             ops: wgpu::Operations {
                                 load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
@@ -529,23 +374,25 @@ impl State {
             render_pass.set_pipeline(&self.render_pipeline);
 
             // The render shader needs access to the field's dimensions...
-            render_pass.set_bind_group(0, &self.size_group, &[]);
+            render_pass.set_bind_group(0, &self.compute.size_group, &[]);
 
-            // ...and the output texture from the compute shader
+            // ...the output texture from the compute shader...
             render_pass.set_bind_group(1, &self.render_texture_group, &[]);
 
+            // ...and the part of it the camera is looking at, along with the gridlines
+            render_pass.set_bind_group(2, &self.camera_group, &[]);
+
             // Setup the vertex and index buffers (which are constant)
             render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
             render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
             
-            // Finally, draw
+            // Finally, draw...
             render_pass.draw_indexed(0..6, 0, 0..1); 
+
+            // ...with any text on top
+            self.overlay.draw(&mut render_pass);
         }
 
         self.queue.submit(iter::once(encoder.finish()));
-        
-        output.present();
-
-        Ok(())
     }
 }
\ No newline at end of file
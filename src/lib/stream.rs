@@ -0,0 +1,78 @@
+use std::io;
+
+use crate::automata;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    // Bare 8-bit RGBA frames, one after another
+    Rgba,
+    // YUV4MPEG2 with full resolution chroma, which most encoders read from a pipe
+    Y4m
+}
+
+// Writes frames read back from a backend's output texture to any sink, like stdout or a file
+// Every frame has to be the size given up front
+pub struct Stream<W: io::Write> {
+    sink: W,
+    format: Format,
+    size: automata::Size,
+    fps: u32,
+    started: bool
+}
+
+// BT.601, limited range
+fn yuv([r, g, b]: [i32; 3]) -> [u8; 3] {
+    [
+        (((66 * r + 129 * g + 25 * b + 128) >> 8) + 16) as u8,
+        (((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128) as u8,
+        (((112 * r - 94 * g - 18 * b + 128) >> 8) + 128) as u8
+    ]
+}
+
+impl<W: io::Write> Stream<W> {
+    pub fn new(sink: W, format: Format, size: automata::Size, fps: u32) -> Self {
+        Self { sink, format, size, fps: fps.max(1), started: false }
+    }
+
+    pub fn write_frame(&mut self, rgba: &[u8]) -> anyhow::Result<()> {
+        let pixels = (self.size.width * self.size.height) as usize;
+        if rgba.len() != pixels * 4 {
+            anyhow::bail!(
+                "expected a {}x{} frame of {} bytes, found {} bytes",
+                self.size.width, self.size.height, pixels * 4, rgba.len()
+            );
+        }
+
+        match self.format {
+            Format::Rgba => self.sink.write_all(rgba)?,
+            Format::Y4m => {
+                if !self.started {
+                    writeln!(
+                        self.sink,
+                        "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444",
+                        self.size.width, self.size.height, self.fps
+                    )?;
+                }
+
+                // Each plane is written in full, one after another
+                let yuv = rgba
+                    .chunks_exact(4)
+                    .map(|pixel| yuv([pixel[0], pixel[1], pixel[2]].map(i32::from)))
+                    .collect::<Vec<_>>();
+
+                self.sink.write_all(b"FRAME\n")?;
+                for plane in 0..3 {
+                    self.sink.write_all(&yuv.iter().map(|pixel| pixel[plane]).collect::<Vec<_>>())?;
+                }
+            }
+        }
+
+        self.started = true;
+
+        anyhow::Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.sink
+    }
+}